@@ -4,20 +4,20 @@ use std::{future::Future, path::PathBuf, sync::Arc, time::Duration};
 
 use shakey::{
     config::Config,
+    console::Console,
     data::Interest,
     env::EnvVar,
-    ext::{Either, FutureExt},
     global::{Global, GlobalItem},
     handler::{Bindable, Components, SharedCallable},
-    irc,
+    irc::{self, ChannelControl, Twitch},
+    supervisor::{Health, Ready, State, Supervisor},
     templates::reset_registry,
-    Commands, Replier, Templates,
+    twilight::Discord,
+    Commands, Platform, Replier, Templates,
 };
 use tokio::task::JoinHandle;
 
-async fn initialize<T>(
-    stop: impl Future<Output = ()> + Send + 'static,
-) -> anyhow::Result<JoinHandle<()>>
+async fn initialize<T>() -> anyhow::Result<JoinHandle<()>>
 where
     T: Default + Send + Sync + 'static,
     T: Interest + for<'de> serde::Deserialize<'de>,
@@ -49,51 +49,64 @@ where
             reload::<T>,
         );
 
-        use shakey::ext::Either::*;
-        match stop.select(fut).await {
-            Left(..) => {}
-            Right(Err(err)) => log::error!("could not reload {}: {err}", T::description()),
-            _ => {}
+        if let Err(err) = fut.await {
+            log::error!("could not reload {}: {err}", T::description())
         }
     }))
 }
 
 struct Modules<'a, R: Replier> {
     components: &'a Components,
-    inner: Vec<SharedCallable<R>>,
+    offline: bool,
+    inner: Vec<(String, SharedCallable<R>)>,
 }
 
 impl<'a, R: Replier> Modules<'a, R> {
-    fn new(components: &'a Components) -> Self {
+    fn new(components: &'a Components, offline: bool) -> Self {
         Self {
             components,
+            offline,
             inner: vec![],
         }
     }
 
     async fn add<T: Bindable<R>>(mut self) -> anyhow::Result<Modules<'a, R>> {
         let binding = T::bind(self.components).await?;
-        self.inner.push(binding.into_callable());
+        self.inner.push((binding.module(), binding.into_callable()));
         Ok(self)
     }
 
-    fn into_list(self) -> Vec<SharedCallable<R>> {
+    /// Adds a module that needs a networked component, unless this is offline
+    async fn add_networked<T: Bindable<R>>(self) -> anyhow::Result<Modules<'a, R>> {
+        if self.offline {
+            log::info!(
+                "skipping {}, it's not available offline",
+                std::any::type_name::<T>()
+            );
+            return Ok(self);
+        }
+        self.add::<T>().await
+    }
+
+    fn into_list(self) -> Vec<(String, SharedCallable<R>)> {
         self.inner
     }
 }
 
+/// Binds all of the modules, `offline` leaves out the ones that need the network
 async fn bind_modules<R: Replier>(
     components: &Components,
-) -> anyhow::Result<Vec<SharedCallable<R>>> {
+    offline: bool,
+) -> anyhow::Result<Vec<(String, SharedCallable<R>)>> {
     use shakey::modules::*;
 
     reset_registry();
-    Ok(Modules::<R>::new(components)
+    Ok(Modules::<R>::new(components, offline)
         .add::<Builtin>()
         .await?
-        .add::<Twitch>()
+        .add_networked::<Twitch>()
         .await?
-        .add::<Spotify>()
+        .add_networked::<Spotify>()
         .await?
         .add::<Crates>()
         .await?
@@ -110,81 +123,77 @@ async fn bind_modules<R: Replier>(
         .into_list())
 }
 
-#[tokio::main(flavor = "current_thread")]
-async fn main() -> anyhow::Result<()> {
-    simple_env_load::load_env_from([".dev.env", ".secrets.env"]);
-
-    alto_logger::init_alt_term_logger()?;
+/// Runs `platform` with the modules, restarting it when it stops
+fn supervise<P: Platform + Clone>(
+    platform: P,
+    modules: Vec<(String, SharedCallable)>,
+) -> (Health, impl Future<Output = ()>) {
+    let supervisor = Supervisor::new(P::NAME);
+    let health = supervisor.health();
+    let task = supervisor.run(move |ready| {
+        let (platform, modules) = (platform.clone(), modules.clone());
+        async move { platform.run(modules, ready).await }
+    });
+    (health, task)
+}
 
-    let config = Config::load("config.yaml").await?;
-    let components = shakey::handler::register_components(&config).await?;
+/// Logs the platforms that aren't running, every so often
+async fn report_health(health: Vec<(&'static str, Health)>) {
+    const EVERY: Duration = Duration::from_secs(5 * 60);
 
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + EVERY, EVERY);
     loop {
-        let notify = Notify::new();
-
-        let commands_task = initialize::<Commands>(notify.notifier()).await?;
-        let templates_task = initialize::<Templates>(notify.notifier()).await?;
-
-        let modules = bind_modules(&components).await?;
-
-        // TODO don't do this in the loop
-        // OR: shut it down before the next iteration
-        let discord = tokio::spawn({
-            let modules = modules.clone();
-            let stop = notify.notifier();
-            async move {
-                match stop.select(shakey::twilight::run(modules)).await {
-                    Either::Left(..) => {}
-                    Either::Right(..) => {}
-                }
+        interval.tick().await;
+        for (name, health) in &health {
+            match health.state() {
+                State::Running => {}
+                state => log::warn!("{name} is not running: {state:?}"),
             }
-        });
-
-        if let Err(err) = async move {
-            shakey::irc::run(modules).await?;
-            anyhow::Result::<_, anyhow::Error>::Ok(())
-        }
-        .await
-        {
-            log::warn!("disconnected");
-            match () {
-                _ if err.is::<irc::errors::Connection>() => {}
-                _ if err.is::<irc::errors::Eof>() => {}
-                _ if err.is::<irc::errors::Timeout>() => {}
-                _ => {
-                    log::error!("{err}");
-                    std::process::exit(1)
-                }
-            }
-
-            notify.notify().await;
-            let _ = tokio::join!(commands_task, templates_task, discord);
-
-            log::warn!("reconnecting in 5 seconds");
-            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
         }
     }
 }
 
-struct Notify {
-    sender: flume::Sender<()>,
-    recv: flume::Receiver<()>,
-}
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> anyhow::Result<()> {
+    simple_env_load::load_env_from([".dev.env", ".secrets.env"]);
 
-impl Notify {
-    fn new() -> Self {
-        let (sender, recv) = flume::bounded(0);
-        Self { sender, recv }
-    }
+    alto_logger::init_alt_term_logger()?;
 
-    fn notifier(&self) -> impl Future<Output = ()> {
-        let recv = self.recv.clone();
-        async move {
-            let _ = recv.recv_async().await;
-        }
-    }
+    // chat from the terminal, rather than from twitch and discord
+    let console = std::env::args().skip(1).any(|arg| arg == "--console");
+
+    let config = Config::load("config.yaml").await?;
+    shakey::set_message_capacity(config.messages.capacity);
+    let components = match console {
+        true => shakey::handler::register_offline_components(&config).await?,
+        false => shakey::handler::register_components(&config).await?,
+    };
 
-    async fn notify(self) {
-        let _ = self.sender.into_send_async(()).await;
+    let _commands_task = initialize::<Commands>().await?;
+    let _templates_task = initialize::<Templates>().await?;
+
+    // modules are only bound once, so their state survives reconnects
+    let modules = bind_modules(&components, console).await?;
+
+    if console {
+        return Console::default().run(modules, Ready::default()).await;
     }
+
+    let twitch = Twitch {
+        settings: irc::Settings::load(&config.twitch)?,
+        control: components.get::<ChannelControl>(),
+    };
+    let discord = Discord {
+        config: config.discord.clone(),
+    };
+
+    let (twitch_health, twitch) = supervise(twitch, modules.clone());
+    let (discord_health, discord) = supervise(discord, modules);
+    let health = report_health(vec![
+        (Twitch::NAME, twitch_health),
+        (Discord::NAME, discord_health),
+    ]);
+
+    tokio::join!(twitch, discord, health);
+    Ok(())
 }
@@ -0,0 +1,131 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+};
+
+/// A map that holds at most `capacity` entries
+///
+/// Once it is full the least recently used entry is evicted. Inserting or
+/// getting an entry counts as using it
+pub struct Bounded<K, V> {
+    capacity: usize,
+    tick: u64,
+    map: HashMap<K, (u64, V)>,
+    order: BTreeMap<u64, K>,
+}
+
+impl<K, V> Bounded<K, V>
+where
+    K: Hash + Eq + Copy,
+{
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            map: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let tick = self.next_tick();
+        let (used, val) = self.map.get_mut(key)?;
+        self.order.remove(used);
+        self.order.insert(tick, *key);
+        *used = tick;
+        Some(val)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Inserts `val`, returning the one it replaced
+    pub fn insert(&mut self, key: K, val: V) -> Option<V> {
+        let tick = self.next_tick();
+        let old = self.map.insert(key, (tick, val)).map(|(used, old)| {
+            self.order.remove(&used);
+            old
+        });
+        self.order.insert(tick, key);
+        self.evict();
+        old
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let (used, val) = self.map.remove(key)?;
+        self.order.remove(&used);
+        Some(val)
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.map.len() > self.capacity {
+            match self.order.pop_first() {
+                Some((_, key)) => self.map.remove(&key),
+                None => break,
+            };
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounded() {
+        let mut map = Bounded::new(100);
+        map.insert(-1, "first".to_string());
+        map.insert(-2, "kept".to_string());
+
+        for i in 0..50_000 {
+            map.insert(i, i.to_string());
+            // using it keeps it around
+            assert!(map.get(&-2).is_some());
+
+            assert!(map.map.len() <= 100);
+            assert_eq!(map.map.len(), map.order.len());
+        }
+
+        assert_eq!(map.get(&-1), None);
+        assert_eq!(map.get(&0), None);
+        assert_eq!(map.get(&-2).map(|s| &**s), Some("kept"));
+        assert_eq!(map.get(&49_999).map(|s| &**s), Some("49999"));
+
+        map.set_capacity(10);
+        assert_eq!(map.map.len(), 10);
+        assert_eq!(map.order.len(), 10);
+        assert!(map.get(&-2).is_some());
+    }
+
+    #[test]
+    fn replace_and_remove() {
+        let mut map = Bounded::new(3);
+        for i in 0..10 {
+            map.insert(i, i.to_string());
+        }
+
+        // replacing an entry doesn't make it count twice
+        assert_eq!(map.insert(9, "nine".into()).as_deref(), Some("9"));
+        assert_eq!(map.order.values().copied().collect::<Vec<_>>(), [7, 8, 9]);
+
+        assert_eq!(map.remove(&8).as_deref(), Some("8"));
+        assert_eq!(map.remove(&8), None);
+        assert!(!map.contains_key(&8));
+
+        map.insert(10, "10".into());
+        map.insert(11, "11".into());
+        assert_eq!(map.order.values().copied().collect::<Vec<_>>(), [9, 10, 11]);
+        assert_eq!(map.get(&9).map(|s| &**s), Some("nine"));
+    }
+}
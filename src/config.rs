@@ -1,3 +1,13 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    path::PathBuf,
+};
+
+use twilight_model::id::{
+    marker::{ChannelMarker, GuildMarker, RoleMarker, UserMarker},
+    Id,
+};
+
 use crate::env::Secret;
 
 #[derive(::serde::Deserialize)]
@@ -17,8 +27,77 @@ pub struct GithubConfig {
     pub oauth_token: Secret,
 }
 
+#[derive(Default, ::serde::Deserialize)]
+pub struct TwitchConfig {
+    /// Connect anonymously and never write to chat
+    #[serde(default)]
+    pub read_only: bool,
+    /// Where replies go in read-only mode, they are logged if this isn't set
+    #[serde(default)]
+    pub sink: Option<PathBuf>,
+}
+
+#[derive(Clone, Default, ::serde::Deserialize)]
+pub struct DiscordConfig {
+    /// These users are treated like the broadcaster, everywhere
+    #[serde(default)]
+    pub admin_users: HashSet<Id<UserMarker>>,
+    /// Members with any of these roles are treated like the broadcaster
+    #[serde(default)]
+    pub admin_roles: HashSet<Id<RoleMarker>>,
+    /// Members with any of these roles are treated like moderators
+    #[serde(default)]
+    pub moderator_roles: HashSet<Id<RoleMarker>>,
+    /// Only these guilds are answered in, every guild is if this is empty
+    #[serde(default)]
+    pub guilds: HashMap<Id<GuildMarker>, GuildConfig>,
+}
+
+#[derive(Clone, Default, ::serde::Deserialize)]
+pub struct GuildConfig {
+    /// Only these channels are answered in, every channel is if this is empty
+    #[serde(default)]
+    pub allow: HashSet<Id<ChannelMarker>>,
+    /// These channels are never answered in
+    #[serde(default)]
+    pub ignore: HashSet<Id<ChannelMarker>>,
+    /// The modules active in this guild, all of them are if this is empty
+    #[serde(default)]
+    pub modules: BTreeSet<String>,
+    #[serde(default)]
+    pub channels: HashMap<Id<ChannelMarker>, ChannelConfig>,
+}
+
+#[derive(Clone, Default, ::serde::Deserialize)]
+pub struct ChannelConfig {
+    /// The modules active in this channel, this replaces the guild's modules
+    #[serde(default)]
+    pub modules: BTreeSet<String>,
+    /// Only commands are handled in this channel, nothing listens to chat
+    #[serde(default)]
+    pub commands_only: bool,
+}
+
+#[derive(::serde::Deserialize)]
+pub struct MessagesConfig {
+    /// How many recent messages are remembered, the least recently used are forgotten first
+    pub capacity: usize,
+}
+
+impl Default for MessagesConfig {
+    fn default() -> Self {
+        Self { capacity: 10_000 }
+    }
+}
+
 #[derive(::serde::Deserialize)]
 pub struct Config {
+    #[serde(default)]
+    pub messages: MessagesConfig,
+    #[serde(default)]
+    pub twitch: TwitchConfig,
+    #[serde(default)]
+    pub discord: DiscordConfig,
     pub helix: HelixConfig,
     pub spotify: SpotifyConfig,
     pub github: GithubConfig,
@@ -0,0 +1,283 @@
+//! A chat in the terminal, for trying out commands and templates locally
+use std::sync::Arc;
+
+use time::OffsetDateTime;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::{
+    global::GlobalItem,
+    handler::SharedCallable,
+    message::SenderPriv,
+    platform::{dispatch, Parts, Platform, Responses},
+    supervisor::Ready,
+    templates::{Embed, Variant},
+    Reply, Response, Templates,
+};
+
+/// Reads chat from stdin and prints the replies to stdout
+///
+/// Lines starting with `:` change who is talking, see [`Meta`]
+#[derive(Clone, Default)]
+pub struct Console {
+    pub variant: Variant,
+}
+
+/// A line typed into the console
+#[derive(Clone, Debug)]
+pub struct Message {
+    pub sender: Arc<str>,
+    pub target: Arc<str>,
+    pub data: Arc<str>,
+    pub timestamp: OffsetDateTime,
+    pub priv_: SenderPriv,
+}
+
+/// Where the replies to a line go, they are rendered with `variant`
+pub struct Outgoing {
+    sender: Arc<str>,
+    target: Arc<str>,
+    variant: Variant,
+}
+
+/// Who is talking, and how replies are shown
+struct Session {
+    sender: Arc<str>,
+    target: Arc<str>,
+    priv_: SenderPriv,
+    variant: Variant,
+}
+
+impl Session {
+    fn apply(&mut self, meta: Meta) {
+        match meta {
+            Meta::As(priv_) => self.priv_ = priv_,
+            Meta::Name(name) => self.sender = name.into(),
+            Meta::Channel(channel) => self.target = channel.into(),
+            Meta::Variant(variant) => self.variant = variant,
+            Meta::Help => {
+                println!("{}", Meta::USAGE);
+                return;
+            }
+        }
+        println!(
+            "[{}] talking as {} ({:?}), rendering with {:?}",
+            self.target, self.sender, self.priv_, self.variant
+        );
+    }
+}
+
+/// A meta-command, these start with `:`
+#[derive(Debug, PartialEq, Eq)]
+enum Meta {
+    /// `:as broadcaster`, `:as moderator`, `:as vip`, `:as subscriber` or `:as viewer`
+    As(SenderPriv),
+    /// `:name someone`
+    Name(String),
+    /// `:channel #somewhere`
+    Channel(String),
+    /// `:variant discord`
+    Variant(Variant),
+    Help,
+}
+
+impl Meta {
+    const USAGE: &'static str = "\
+        :as broadcaster|moderator|vip|subscriber|viewer -- changes the privilege of the sender\n\
+        :name <name>                                    -- changes the name of the sender\n\
+        :channel <channel>                              -- changes the channel being talked in\n\
+        :variant default|discord|embed                  -- changes how replies are rendered\n\
+        :help                                           -- shows this";
+
+    /// Parses a meta-command, this is `None` if `line` isn't one
+    fn parse(line: &str) -> Option<Result<Self, String>> {
+        let line = line.strip_prefix(':')?;
+        let (head, tail) = line.split_once(' ').unwrap_or((line, ""));
+        let tail = tail.trim();
+
+        let meta = match (head, tail) {
+            ("as", "broadcaster") => Self::As(SenderPriv::Admin),
+            ("as", "moderator") => Self::As(SenderPriv::Moderator),
+            ("as", "vip") => Self::As(SenderPriv::Vip),
+            ("as", "subscriber") => Self::As(SenderPriv::Subscriber),
+            ("as", "viewer") => Self::As(SenderPriv::None),
+            ("name" | "channel", "") => return Some(Err(format!("{head} needs a value"))),
+            ("name", name) => Self::Name(name.to_string()),
+            ("channel", channel) => Self::Channel(channel.to_string()),
+            ("variant", "default") => Self::Variant(Variant::Default),
+            ("variant", "discord") => Self::Variant(Variant::Discord),
+            ("variant", "embed") => Self::Variant(Variant::Embed),
+            ("help", "") => Self::Help,
+            _ => return Some(Err(format!("unknown meta-command: {line}"))),
+        };
+        Some(Ok(meta))
+    }
+}
+
+#[async_trait::async_trait]
+impl Platform for Console {
+    type Message = Message;
+    // the variant can change while running, so this is rendered when it is delivered
+    type Rendered = Box<dyn Response>;
+    type Outgoing = Outgoing;
+
+    const NAME: &'static str = "console";
+    const VARIANT: Variant = Variant::Default;
+    const WHISPERS: bool = true;
+
+    /// Runs until stdin is closed
+    async fn run(
+        &self,
+        handlers: Vec<(String, SharedCallable)>,
+        ready: Ready,
+    ) -> anyhow::Result<()> {
+        let handlers = handlers
+            .into_iter()
+            .map(|(_, handler)| handler)
+            .collect::<Vec<_>>();
+
+        let mut session = Session {
+            sender: "viewer".into(),
+            target: "#console".into(),
+            priv_: SenderPriv::None,
+            variant: self.variant,
+        };
+        println!("type :help for the meta-commands");
+        ready.ready();
+
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        while let Some(line) = lines.next_line().await? {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            match Meta::parse(line) {
+                Some(Ok(meta)) => session.apply(meta),
+                Some(Err(err)) => println!("{err}\n{}", Meta::USAGE),
+                None => {
+                    let msg = Message {
+                        sender: session.sender.clone(),
+                        target: session.target.clone(),
+                        data: line.into(),
+                        timestamp: OffsetDateTime::now_utc(),
+                        priv_: session.priv_,
+                    };
+                    let out = Outgoing {
+                        sender: session.sender.clone(),
+                        target: session.target.clone(),
+                        variant: session.variant,
+                    };
+                    dispatch::<Self>(msg, &handlers, out);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parts(msg: &Self::Message) -> Parts {
+        Parts {
+            sender: msg.sender.clone(),
+            target: msg.target.clone(),
+            data: msg.data.clone(),
+            timestamp: msg.timestamp,
+            tags: None,
+            expects_reply: false,
+        }
+    }
+
+    fn sender_priv(msg: &Self::Message) -> SenderPriv {
+        msg.priv_
+    }
+
+    fn render(resp: Box<dyn Response>) -> Option<Self::Rendered> {
+        Some(resp)
+    }
+
+    async fn deliver(out: Self::Outgoing, mut responses: Responses<Self>) {
+        let Outgoing {
+            sender,
+            target,
+            variant,
+        } = out;
+
+        while let Some(resp) = responses.next().await {
+            let resp = match resp.map(|resp| render(resp, variant)).transpose() {
+                Some(resp) => resp,
+                None => continue,
+            };
+
+            let line = match resp {
+                Reply::Say(resp) => resp,
+                Reply::Reply(resp) => format!("{sender}: {resp}"),
+                Reply::Problem(resp) => format!("(problem) {sender}: {resp}"),
+                Reply::Action(resp) => format!("* {resp}"),
+                Reply::Whisper(resp) => format!("(whisper) {sender}: {resp}"),
+            };
+            println!("[{target}] {line}");
+        }
+    }
+}
+
+/// Embeds are shown as plain text, one line per part
+fn render(resp: Box<dyn Response>, variant: Variant) -> Option<String> {
+    let templates = Templates::get();
+    match variant {
+        Variant::Embed => match templates.render_embed(&resp) {
+            Some(embed) => Some(format_embed(embed)),
+            None => templates.render(&resp, Variant::Discord),
+        },
+        variant => templates.render(&resp, variant),
+    }
+}
+
+fn format_embed(embed: Embed) -> String {
+    let title = match (embed.title, embed.url) {
+        (Some(title), Some(url)) => Some(format!("{title} <{url}>")),
+        (title, url) => title.or(url),
+    };
+
+    title
+        .into_iter()
+        .chain(embed.description)
+        .chain(
+            embed
+                .fields
+                .into_iter()
+                .map(|field| format!("{}: {}", field.name, field.value)),
+        )
+        .chain(embed.footer.map(|footer| format!("-- {footer}")))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn meta() {
+        assert_eq!(Meta::parse("!hello"), None);
+        assert_eq!(
+            Meta::parse(":as broadcaster"),
+            Some(Ok(Meta::As(SenderPriv::Admin)))
+        );
+        assert_eq!(
+            Meta::parse(":as moderator"),
+            Some(Ok(Meta::As(SenderPriv::Moderator)))
+        );
+        assert_eq!(
+            Meta::parse(":name  museun "),
+            Some(Ok(Meta::Name("museun".into())))
+        );
+        assert_eq!(
+            Meta::parse(":variant embed"),
+            Some(Ok(Meta::Variant(Variant::Embed)))
+        );
+        assert_eq!(Meta::parse(":help"), Some(Ok(Meta::Help)));
+
+        assert!(matches!(Meta::parse(":as admin"), Some(Err(..))));
+        assert!(matches!(Meta::parse(":name"), Some(Err(..))));
+        assert!(matches!(Meta::parse(":variant"), Some(Err(..))));
+    }
+}
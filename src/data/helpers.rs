@@ -20,7 +20,11 @@ where
     T: serde::Serialize + Send + Sync,
 {
     let data = serde_yaml::to_string(val)?;
-    tokio::fs::write(T::get_path(root), data).await?;
+    let path = T::get_path(root);
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path, data).await?;
     Ok(())
 }
 
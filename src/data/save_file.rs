@@ -15,6 +15,12 @@ impl<T, const FORMAT: u8> SaveFile<T, FORMAT>
 where
     T: Interest,
 {
+    pub fn new(data: T) -> Self {
+        Self {
+            data: Arc::new(RwLock::new(data)),
+        }
+    }
+
     pub async fn save(&self) -> anyhow::Result<()>
     where
         T: serde::Serialize + Send + Sync,
@@ -33,7 +33,7 @@ make_env_key! {
 
     /// The name of the bot for Twitch
     SHAKEN_TWITCH_NAME
-    /// The address of the Twitch server
+    /// The address of the Twitch server, use `tls://` for a TLS connection
     SHAKEN_TWITCH_ADDRESS
     /// Comma-separated list of channels to join
     SHAKEN_TWITCH_CHANNELS
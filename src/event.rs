@@ -0,0 +1,129 @@
+use std::{sync::Arc, time::Duration};
+
+use serde::Serialize;
+use time::OffsetDateTime;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{irc::Tags, Replier, Reply, Response};
+
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum EventKind {
+    Subscription {
+        user: Arc<str>,
+        months: u32,
+        plan: Arc<str>,
+        message: Option<Arc<str>>,
+    },
+    GiftSubscription {
+        /// This is `None` for anonymous gifts
+        gifter: Option<Arc<str>>,
+        recipient: Arc<str>,
+        months: u32,
+        plan: Arc<str>,
+    },
+    Raid {
+        user: Arc<str>,
+        viewers: u64,
+    },
+    /// Any other kind of user notice, like an announcement
+    UserNotice {
+        kind: Arc<str>,
+        user: Option<Arc<str>>,
+        system_message: Option<Arc<str>>,
+        message: Option<Arc<str>>,
+    },
+    Timeout {
+        user: Arc<str>,
+        duration: Duration,
+    },
+    Ban {
+        user: Arc<str>,
+    },
+    ClearChat,
+    MessageDeleted {
+        user: Arc<str>,
+        id: Arc<str>,
+        data: Arc<str>,
+    },
+    /// Only the settings that changed are `Some`
+    RoomState {
+        emote_only: Option<bool>,
+        subs_only: Option<bool>,
+        /// The minimum follow age, `Some(None)` if followers-only mode was turned off
+        followers_only: Option<Option<Duration>>,
+        /// `Some(Duration::ZERO)` if slow mode was turned off
+        slow: Option<Duration>,
+    },
+    Notice {
+        kind: Option<Arc<str>>,
+        data: Arc<str>,
+    },
+}
+
+pub struct Event<R: Replier> {
+    pub(crate) kind: Arc<EventKind>,
+    pub(crate) target: Arc<str>,
+    pub(crate) timestamp: OffsetDateTime,
+    pub(crate) tags: Option<Arc<Tags>>,
+    pub(crate) reply: UnboundedSender<Reply<R>>,
+}
+
+impl<R: Replier> std::fmt::Debug for Event<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Event")
+            .field("kind", &self.kind)
+            .field("target", &self.target)
+            .finish()
+    }
+}
+
+impl<R: Replier + 'static> Clone for Event<R> {
+    fn clone(&self) -> Self {
+        Self {
+            kind: self.kind.clone(),
+            target: self.target.clone(),
+            timestamp: self.timestamp,
+            tags: self.tags.clone(),
+            reply: self.reply.clone(),
+        }
+    }
+}
+
+impl<R: Replier> Event<R> {
+    pub(crate) fn twitch(
+        kind: EventKind,
+        target: impl Into<Arc<str>>,
+        tags: Tags,
+        reply: UnboundedSender<Reply<R>>,
+    ) -> Self {
+        Self {
+            kind: Arc::new(kind),
+            target: target.into(),
+            timestamp: OffsetDateTime::now_utc(),
+            tags: Some(Arc::new(tags)),
+            reply,
+        }
+    }
+
+    pub fn say(&self, item: impl Serialize + Response + 'static) {
+        let item = R::say(item);
+        let _ = self.reply.send(item);
+    }
+
+    pub fn kind(&self) -> &EventKind {
+        &self.kind
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub const fn timestamp(&self) -> OffsetDateTime {
+        self.timestamp
+    }
+
+    pub fn tags(&self) -> Option<&Tags> {
+        self.tags.as_deref()
+    }
+}
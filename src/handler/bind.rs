@@ -1,6 +1,7 @@
 use std::{
     collections::{BTreeSet, HashMap},
     sync::Arc,
+    time::{Duration, Instant},
 };
 
 use anyhow::Context;
@@ -9,15 +10,18 @@ use heck::ToSnekCase;
 use crate::{
     data::{Interest, InterestPath},
     global::GlobalItem,
-    responses, Arguments, Message, Outcome, RegisterResponse, Replier,
+    message::SenderPriv,
+    responses, Arguments, Event, Message, Outcome, RegisterResponse, Replier,
 };
 
 use super::{
     arguments::{ExampleArgs, Match},
-    Bindable, SharedCallable,
+    Bindable, Incoming, SharedCallable,
 };
 
-type BoxedHandler<R> = Box<dyn Fn(&Message<R>) + Send + Sync>;
+/// The `bool` is whether the message was edited, see [`Incoming::Edit`]
+type BoxedHandler<R> = Box<dyn Fn(&Message<R>, bool) + Send + Sync>;
+type BoxedEventHandler<R> = Box<dyn Fn(&Event<R>) + Send + Sync>;
 
 pub struct Bind<T, R>
 where
@@ -25,7 +29,10 @@ where
     R: Replier + 'static,
 {
     this: Arc<parking_lot::Mutex<T>>,
+    /// These run again when a message is edited
     handlers: Vec<BoxedHandler<R>>,
+    listeners: Vec<BoxedHandler<R>>,
+    event_handlers: Vec<BoxedEventHandler<R>>,
 }
 
 impl<T, R> Bind<T, R>
@@ -41,10 +48,26 @@ where
         Ok(Self {
             this: Arc::new(parking_lot::Mutex::new(this)),
             handlers: vec![],
+            listeners: vec![],
+            event_handlers: vec![],
         })
     }
 
-    pub fn bind<O, F>(mut self, handler: F) -> anyhow::Result<Self>
+    /// Binds a command that everyone can use, unless `commands.yaml` says otherwise
+    pub fn bind<O, F>(self, handler: F) -> anyhow::Result<Self>
+    where
+        O: Outcome + 'static,
+        F: Fn(&mut T, &Message<R>, Arguments) -> O + Send + Sync + 'static,
+        F: Copy + 'static,
+    {
+        self.bind_with(Permission::Everyone, handler)
+    }
+
+    /// Binds a command that needs `permission`, unless `commands.yaml` says otherwise
+    ///
+    /// Privileged commands should use this, so they aren't open to everyone when
+    /// their `permission` is missing from `commands.yaml`
+    pub fn bind_with<O, F>(mut self, permission: Permission, handler: F) -> anyhow::Result<Self>
     where
         O: Outcome + 'static,
         F: Fn(&mut T, &Message<R>, Arguments) -> O + Send + Sync + 'static,
@@ -56,16 +79,46 @@ where
             .find(&module, &key)
             .with_context(|| anyhow::anyhow!("cannot find {module}.{key}"))?;
 
+        // this lives with the handler, so it outlasts reloads of `commands.yaml`
+        let cooldowns = parking_lot::Mutex::new(Cooldowns::default());
+
         let this = Arc::clone(&self.this);
-        let this = move |msg: &Message<R>| {
+        let this = move |msg: &Message<R>, edited: bool| {
             let cmd = Commands::get();
             let cmd = cmd.find(&module, &key).expect("command should exist");
 
+            let required = cmd.permission.unwrap_or(permission);
+            if cmd.without_command(&msg.data).is_none() || !Self::check_permission(required, msg) {
+                return;
+            }
+
             let map = match Self::parse_command(cmd, msg) {
                 Some(map) => map,
                 None => return,
             };
 
+            // an edit runs the command again in place of the first run, so it doesn't count as a use
+            if !edited && !msg.is_from_elevated() {
+                let now = Instant::now();
+                let mut cooldowns = cooldowns.lock();
+                if let Some(left) = cooldowns.check(cmd, &msg.target, &msg.sender, now) {
+                    // the platform shows an error if nothing is said, so they are always told
+                    let reply = match msg.expects_reply() {
+                        true => true,
+                        false => {
+                            cmd.cooldown_reply
+                                && cooldowns.should_reply(&msg.target, &msg.sender, left, now)
+                        }
+                    };
+                    if reply {
+                        msg.problem(responses::OnCooldown {
+                            seconds: left.as_secs_f64().ceil() as u64,
+                        })
+                    }
+                    return;
+                }
+            }
+
             let this = &mut *this.lock();
             let outcome = handler(this, msg, map);
 
@@ -93,32 +146,101 @@ where
         Ok(self)
     }
 
+    /// Sees every message, but not their edits
     pub fn listen<O, F>(mut self, handler: F) -> anyhow::Result<Self>
+    where
+        O: Outcome + 'static,
+        F: Fn(&mut T, &Message<R>) -> O + Send + Sync + 'static + Copy,
+    {
+        let listener = self.listener(handler);
+        self.listeners.push(listener);
+        Ok(self)
+    }
+
+    /// Like [`Self::listen`], for commands that aren't in `commands.yaml`. These run again when a message is edited
+    pub fn listen_for_commands<O, F>(mut self, handler: F) -> anyhow::Result<Self>
+    where
+        O: Outcome + 'static,
+        F: Fn(&mut T, &Message<R>) -> O + Send + Sync + 'static + Copy,
+    {
+        let listener = self.listener(handler);
+        self.handlers.push(listener);
+        Ok(self)
+    }
+
+    fn listener<O, F>(&self, handler: F) -> BoxedHandler<R>
     where
         O: Outcome + 'static,
         F: Fn(&mut T, &Message<R>) -> O + Send + Sync + 'static + Copy,
     {
         let this = Arc::clone(&self.this);
-        let this = move |msg: &Message<R>| {
+        let this = move |msg: &Message<R>, _edited: bool| {
             let this = &mut *this.lock();
             if let Some(error) = handler(this, msg).into_error() {
                 msg.problem(responses::Error { error })
             }
         };
+        Box::new(this) as _
+    }
 
-        self.handlers.push(Box::new(this) as _);
+    pub fn event<O, F>(mut self, handler: F) -> anyhow::Result<Self>
+    where
+        O: Outcome + 'static,
+        F: Fn(&mut T, &Event<R>) -> O + Send + Sync + 'static + Copy,
+    {
+        let this = Arc::clone(&self.this);
+        let this = move |event: &Event<R>| {
+            let this = &mut *this.lock();
+            if let Some(error) = handler(this, event).into_error() {
+                log::warn!("cannot handle event {:?}: {error}", event.kind())
+            }
+        };
+
+        self.event_handlers.push(Box::new(this) as _);
         Ok(self)
     }
 
+    /// The name of this module in `commands.yaml`
+    pub fn module(&self) -> String {
+        Self::make_keyable::<()>().0
+    }
+
     pub fn into_callable(self) -> SharedCallable<R> {
-        Arc::new(move |msg: crate::Message<R>| {
-            for handler in &self.handlers {
-                // outcome is always () here
-                (handler)(&msg);
+        Arc::new(move |incoming: Incoming<R>| match incoming {
+            Incoming::Message(msg) => {
+                for handler in self.handlers.iter().chain(&self.listeners) {
+                    // outcome is always () here
+                    (handler)(&msg, false);
+                }
+            }
+            Incoming::Edit(msg) => {
+                for handler in &self.handlers {
+                    (handler)(&msg, true);
+                }
+            }
+            Incoming::Event(event) => {
+                for handler in &self.event_handlers {
+                    (handler)(&event);
+                }
             }
         }) as _
     }
 
+    /// Tells the sender when they aren't allowed to use the command
+    fn check_permission(required: Permission, msg: &Message<R>) -> bool {
+        if msg.permission() >= required {
+            return true;
+        }
+
+        match required {
+            Permission::Broadcaster => msg.problem(responses::RequiresAdmin {}),
+            permission => msg.problem(responses::RequiresPermission {
+                permission: permission.as_str().to_string(),
+            }),
+        }
+        false
+    }
+
     fn parse_command(cmd: &Command, msg: &Message<R>) -> Option<Arguments> {
         if !cmd.has_args() && cmd.is_command_match(&msg.data) {
             return Some(Arguments::default());
@@ -162,6 +284,117 @@ pub struct Command {
     pub aliases: BTreeSet<String>,
     #[serde(default)]
     pub args: ExampleArgs,
+    /// Who can use it, this replaces the permission it was bound with
+    #[serde(default)]
+    pub permission: Option<Permission>,
+    /// How long everyone has to wait between uses, in a channel
+    #[serde(default, with = "crate::serde::simple_human_time")]
+    pub cooldown: Duration,
+    /// How long each user has to wait between their uses, in a channel
+    #[serde(default, with = "crate::serde::simple_human_time")]
+    pub user_cooldown: Duration,
+    /// Tell the sender how long is left, rather than ignoring them
+    #[serde(default)]
+    pub cooldown_reply: bool,
+}
+
+/// When a command was last used, by channel and by user in a channel
+#[derive(Default)]
+struct Cooldowns {
+    channels: HashMap<Arc<str>, Instant>,
+    users: HashMap<(Arc<str>, Arc<str>), Instant>,
+    /// Until when a user has been told about a cooldown
+    told: HashMap<(Arc<str>, Arc<str>), Instant>,
+}
+
+impl Cooldowns {
+    /// How long is left on the command's cooldowns, they start over if nothing is left
+    fn check(
+        &mut self,
+        cmd: &Command,
+        target: &Arc<str>,
+        sender: &Arc<str>,
+        now: Instant,
+    ) -> Option<Duration> {
+        let remaining = |last: Option<&Instant>, cooldown: Duration| {
+            let elapsed = now.saturating_duration_since(*last?);
+            Some(cooldown.saturating_sub(elapsed)).filter(|left| !left.is_zero())
+        };
+
+        let user = (target.clone(), sender.clone());
+        let left = remaining(self.channels.get(target), cmd.cooldown)
+            .max(remaining(self.users.get(&user), cmd.user_cooldown));
+        if left.is_some() {
+            return left;
+        }
+
+        if !cmd.cooldown.is_zero() {
+            self.channels.insert(target.clone(), now);
+        }
+        if !cmd.user_cooldown.is_zero() {
+            // forget anyone whose cooldown is over, so this doesn't keep growing
+            self.users
+                .retain(|_, last| now.saturating_duration_since(*last) < cmd.user_cooldown);
+            self.users.insert(user, now);
+        }
+        None
+    }
+
+    /// Whether to tell the sender that `left` is left on the cooldown
+    ///
+    /// They are only told once until the cooldown is over, so trying again doesn't spam the chat
+    fn should_reply(
+        &mut self,
+        target: &Arc<str>,
+        sender: &Arc<str>,
+        left: Duration,
+        now: Instant,
+    ) -> bool {
+        let user = (target.clone(), sender.clone());
+        if self.told.get(&user).filter(|&&until| now < until).is_some() {
+            return false;
+        }
+
+        self.told.retain(|_, until| now < *until);
+        self.told.insert(user, now + left);
+        true
+    }
+}
+
+/// Who can use a command, everyone above a level can also use it
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    #[default]
+    Everyone,
+    Subscriber,
+    Vip,
+    Moderator,
+    Broadcaster,
+}
+
+impl Permission {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Everyone => "everyone",
+            Self::Subscriber => "subscriber",
+            Self::Vip => "vip",
+            Self::Moderator => "moderator",
+            Self::Broadcaster => "broadcaster",
+        }
+    }
+}
+
+impl From<SenderPriv> for Permission {
+    fn from(priv_: SenderPriv) -> Self {
+        match priv_ {
+            SenderPriv::Admin => Self::Broadcaster,
+            SenderPriv::Moderator => Self::Moderator,
+            SenderPriv::Vip => Self::Vip,
+            SenderPriv::Subscriber => Self::Subscriber,
+            SenderPriv::None => Self::Everyone,
+        }
+    }
 }
 
 impl Command {
@@ -231,4 +464,269 @@ impl Commands {
     pub fn find(&self, module: &str, key: &str) -> Option<&Command> {
         self.modules.get(module)?.entries.get(key)
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Command> {
+        self.modules
+            .values()
+            .flat_map(|module| module.entries.values())
+    }
+
+    /// Every command, along with the name of its module
+    pub fn with_modules(&self) -> impl Iterator<Item = (&str, &Command)> {
+        self.modules
+            .iter()
+            .flat_map(|(name, module)| module.entries.values().map(move |cmd| (name.as_str(), cmd)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn cooldowns() {
+        let cmd: Command = serde_yaml::from_str(
+            r#"
+command: "!crate"
+description: "tries to look up a crate on crates.io"
+cooldown: "10 seconds"
+user_cooldown: "1 minutes"
+"#,
+        )
+        .unwrap();
+        assert_eq!(cmd.cooldown, Duration::from_secs(10));
+        assert_eq!(cmd.user_cooldown, Duration::from_secs(60));
+
+        let (channel, other) = (Arc::from("#museun"), Arc::from("#other"));
+        let (alice, bob) = (Arc::from("alice"), Arc::from("bob"));
+
+        let mut cooldowns = Cooldowns::default();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert_eq!(cooldowns.check(&cmd, &channel, &alice, at(0)), None);
+        assert_eq!(
+            cooldowns.check(&cmd, &channel, &bob, at(4)),
+            Some(Duration::from_secs(6))
+        );
+        // each channel has its own cooldowns
+        assert_eq!(cooldowns.check(&cmd, &other, &alice, at(4)), None);
+
+        assert_eq!(cooldowns.check(&cmd, &channel, &bob, at(10)), None);
+        assert_eq!(
+            cooldowns.check(&cmd, &channel, &alice, at(20)),
+            Some(Duration::from_secs(40))
+        );
+        assert_eq!(cooldowns.check(&cmd, &channel, &alice, at(60)), None);
+
+        let cmd: Command = serde_yaml::from_str(
+            r#"
+command: "!hello"
+description: "sends a greeting"
+"#,
+        )
+        .unwrap();
+        assert_eq!(cooldowns.check(&cmd, &channel, &alice, at(60)), None);
+        assert_eq!(cooldowns.check(&cmd, &channel, &alice, at(60)), None);
+    }
+
+    #[test]
+    fn cooldown_reply_once() {
+        let (channel, alice, bob) = (Arc::from("#museun"), Arc::from("alice"), Arc::from("bob"));
+        let left = Duration::from_secs(30);
+
+        let mut cooldowns = Cooldowns::default();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(cooldowns.should_reply(&channel, &alice, left, at(0)));
+        assert!(!cooldowns.should_reply(&channel, &alice, left, at(1)));
+        assert!(!cooldowns.should_reply(&channel, &alice, left, at(29)));
+        // everyone is told once
+        assert!(cooldowns.should_reply(&channel, &bob, left, at(1)));
+
+        // and again, once the cooldown they were told about is over
+        assert!(cooldowns.should_reply(&channel, &alice, left, at(30)));
+        assert!(!cooldowns.should_reply(&channel, &alice, left, at(31)));
+    }
+
+    #[test]
+    fn permission() {
+        let cmd: Command = serde_yaml::from_str(
+            r#"
+command: "!add"
+args: "<command> <body..>"
+description: "add a new command with a body"
+permission: moderator
+"#,
+        )
+        .unwrap();
+        assert_eq!(cmd.permission, Some(Permission::Moderator));
+
+        let check = |priv_| {
+            let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+            let msg = crate::console::Message {
+                sender: "someone".into(),
+                target: "#museun".into(),
+                data: "!add !hello world".into(),
+                timestamp: time::OffsetDateTime::now_utc(),
+                priv_,
+            };
+            let msg = Message::<Box<[u8]>>::new::<crate::console::Console>(msg, tx);
+            let allowed = Bind::<(), Box<[u8]>>::check_permission(cmd.permission.unwrap(), &msg);
+            let reply = rx.try_recv().ok().map(|reply| match reply {
+                crate::Reply::Problem(data) => String::from_utf8(data.into()).unwrap(),
+                _ => panic!("the denial should be a problem"),
+            });
+            (allowed, reply)
+        };
+
+        for priv_ in [SenderPriv::None, SenderPriv::Subscriber, SenderPriv::Vip] {
+            let (allowed, reply) = check(priv_);
+            assert!(!allowed, "{priv_:?} should be denied");
+            let reply = serde_yaml::from_str::<serde_yaml::Value>(&reply.unwrap()).unwrap();
+            assert_eq!(reply["permission"].as_str(), Some("moderator"));
+        }
+
+        for priv_ in [SenderPriv::Moderator, SenderPriv::Admin] {
+            assert_eq!(check(priv_), (true, None), "{priv_:?} should be allowed");
+        }
+    }
+
+    // this is bound like the real module, so it finds its commands
+    #[derive(Default)]
+    struct Builtin {
+        commands: Arc<AtomicUsize>,
+        listened: Arc<AtomicUsize>,
+    }
+
+    // the responses can only be registered once, and the other tests do that
+    struct NoResponses;
+    impl RegisterResponse for NoResponses {
+        fn register() -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl<R: Replier> Bindable<R> for Builtin {
+        type Responses = NoResponses;
+        async fn bind(_: &super::super::Components) -> anyhow::Result<Bind<Self, R>> {
+            unreachable!()
+        }
+    }
+
+    impl Builtin {
+        fn hello(&mut self, _: &Message<impl Replier>, _: Arguments) {
+            self.commands.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn listen(&mut self, _: &Message<impl Replier>) {
+            self.listened.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn load_commands() {
+        let commands = serde_yaml::from_str(include_str!("../../commands.yaml")).unwrap();
+        Commands::get_static().initialize(Arc::new(commands));
+    }
+
+    fn message(data: &str, priv_: SenderPriv) -> Message<Box<[u8]>> {
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let msg = crate::console::Message {
+            sender: "someone".into(),
+            target: "#museun".into(),
+            data: data.into(),
+            timestamp: time::OffsetDateTime::now_utc(),
+            priv_,
+        };
+        Message::new::<crate::console::Console>(msg, tx)
+    }
+
+    #[test]
+    fn edits_only_run_commands() {
+        load_commands();
+        let msg = message("!hello", SenderPriv::None);
+
+        let this = Builtin::default();
+        let (commands, listened) = (this.commands.clone(), this.listened.clone());
+        let callable = Bind::<_, Box<[u8]>>::create(this)
+            .unwrap()
+            .bind(Builtin::hello)
+            .unwrap()
+            .listen(Builtin::listen)
+            .unwrap()
+            .into_callable();
+
+        callable(Incoming::Message(msg.clone()));
+        assert_eq!(commands.load(Ordering::SeqCst), 1);
+        assert_eq!(listened.load(Ordering::SeqCst), 1);
+
+        callable(Incoming::Edit(msg));
+        assert_eq!(commands.load(Ordering::SeqCst), 2);
+        assert_eq!(listened.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn bound_permission() {
+        // `!hello` doesn't have a permission in commands.yaml
+        load_commands();
+        let this = Builtin::default();
+        let commands = this.commands.clone();
+        let callable = Bind::<_, Box<[u8]>>::create(this)
+            .unwrap()
+            .bind_with(Permission::Moderator, Builtin::hello)
+            .unwrap()
+            .into_callable();
+
+        callable(Incoming::Message(message("!hello", SenderPriv::Vip)));
+        assert_eq!(commands.load(Ordering::SeqCst), 0);
+
+        callable(Incoming::Message(message("!hello", SenderPriv::Moderator)));
+        assert_eq!(commands.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn edits_skip_cooldowns() {
+        // `!uptime` has a user cooldown, and doesn't reply when it's on one
+        #[derive(Default)]
+        struct Twitch {
+            commands: Arc<AtomicUsize>,
+        }
+
+        #[async_trait::async_trait]
+        impl<R: Replier> Bindable<R> for Twitch {
+            type Responses = NoResponses;
+            async fn bind(_: &super::super::Components) -> anyhow::Result<Bind<Self, R>> {
+                unreachable!()
+            }
+        }
+
+        impl Twitch {
+            fn uptime(&mut self, _: &Message<impl Replier>, _: Arguments) {
+                self.commands.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        load_commands();
+        let this = Twitch::default();
+        let commands = this.commands.clone();
+        let callable = Bind::<_, Box<[u8]>>::create(this)
+            .unwrap()
+            .bind(Twitch::uptime)
+            .unwrap()
+            .into_callable();
+
+        let msg = message("!uptime", SenderPriv::None);
+        callable(Incoming::Message(msg.clone()));
+        assert_eq!(commands.load(Ordering::SeqCst), 1);
+
+        callable(Incoming::Edit(msg.clone()));
+        assert_eq!(commands.load(Ordering::SeqCst), 2);
+
+        callable(Incoming::Message(msg));
+        assert_eq!(commands.load(Ordering::SeqCst), 2);
+    }
 }
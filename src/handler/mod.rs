@@ -8,7 +8,7 @@ mod outcome;
 pub use outcome::{MaybeTask, Outcome};
 
 mod bind;
-pub use bind::{Bind, Commands};
+pub use bind::{Bind, Command, Commands, Permission};
 
 mod response;
 pub use response::Response;
@@ -17,7 +17,7 @@ mod reply;
 pub use reply::Reply;
 
 mod arguments;
-pub use arguments::Arguments;
+pub use arguments::{Arguments, Kind};
 
 mod replier;
 pub use replier::Replier;
@@ -56,7 +56,6 @@ impl Components {
 }
 
 pub async fn register_components(config: &crate::config::Config) -> anyhow::Result<Components> {
-    use crate::github::GistClient;
     use crate::helix::{EmoteMap, HelixClient, OAuth};
     use crate::spotify::SpotifyClient;
 
@@ -85,15 +84,39 @@ pub async fn register_components(config: &crate::config::Config) -> anyhow::Resu
     )
     .await?;
 
+    Ok(local_components(config)
+        .await?
+        .register(helix_client)
+        .register(emote_map)
+        .register(spotify_client))
+}
+
+/// The components for chatting from the terminal, none of these connect to anything
+///
+/// There are no Twitch or Spotify clients, and no emotes are known
+pub async fn register_offline_components(
+    config: &crate::config::Config,
+) -> anyhow::Result<Components> {
+    use crate::helix::EmoteMap;
+
+    Ok(local_components(config)
+        .await?
+        .register(Arc::new(EmoteMap::default())))
+}
+
+async fn local_components(config: &crate::config::Config) -> anyhow::Result<Components> {
+    use crate::github::GistClient;
+    use crate::irc::ChannelControl;
+
     let gist_client = GistClient::new(
         &config.github.oauth_token, //
     );
 
+    let channels = ChannelControl::load().await?;
+
     Ok(Components::default() //
-        .register(helix_client)
-        .register(emote_map)
-        .register(spotify_client)
-        .register(gist_client))
+        .register(gist_client)
+        .register(channels))
 }
 
 #[async_trait::async_trait]
@@ -102,4 +125,11 @@ pub trait Bindable<R: Replier>: Sized + Send + Sync + 'static {
     async fn bind(components: &Components) -> anyhow::Result<Bind<Self, R>>;
 }
 
-pub type SharedCallable<R = Box<dyn Response>> = Arc<dyn Fn(crate::Message<R>) + Send + Sync>;
+pub enum Incoming<R: Replier> {
+    Message(crate::Message<R>),
+    /// A message that was edited, only the commands run again for it
+    Edit(crate::Message<R>),
+    Event(crate::Event<R>),
+}
+
+pub type SharedCallable<R = Box<dyn Response>> = Arc<dyn Fn(Incoming<R>) + Send + Sync>;
@@ -6,6 +6,8 @@ pub trait Replier: Send + Sync + Sized + 'static {
     fn say(item: impl Serialize + Response + 'static) -> Reply<Self>;
     fn reply(item: impl Serialize + Response + 'static) -> Reply<Self>;
     fn problem(item: impl Serialize + Response + 'static) -> Reply<Self>;
+    fn action(item: impl Serialize + Response + 'static) -> Reply<Self>;
+    fn whisper(item: impl Serialize + Response + 'static) -> Reply<Self>;
 }
 
 impl Replier for Box<dyn Response> {
@@ -20,6 +22,14 @@ impl Replier for Box<dyn Response> {
     fn problem(item: impl Serialize + Response + 'static) -> Reply<Self> {
         Reply::Problem(Box::new(item) as _)
     }
+
+    fn action(item: impl Serialize + Response + 'static) -> Reply<Self> {
+        Reply::Action(Box::new(item) as _)
+    }
+
+    fn whisper(item: impl Serialize + Response + 'static) -> Reply<Self> {
+        Reply::Whisper(Box::new(item) as _)
+    }
 }
 
 fn erase(item: impl Serialize + Response + 'static) -> Box<[u8]> {
@@ -40,4 +50,12 @@ impl Replier for Box<[u8]> {
     fn problem(item: impl Serialize + Response + 'static) -> Reply<Self> {
         Reply::Problem(erase(item))
     }
+
+    fn action(item: impl Serialize + Response + 'static) -> Reply<Self> {
+        Reply::Action(erase(item))
+    }
+
+    fn whisper(item: impl Serialize + Response + 'static) -> Reply<Self> {
+        Reply::Whisper(erase(item))
+    }
 }
@@ -3,6 +3,10 @@ pub enum Reply<T> {
     Say(T),
     Reply(T),
     Problem(T),
+    /// Like a `/me` on Twitch
+    Action(T),
+    /// Sent privately to the sender, if the platform allows it
+    Whisper(T),
 }
 
 impl<T> Reply<Option<T>> {
@@ -11,6 +15,8 @@ impl<T> Reply<Option<T>> {
             Self::Say(inner) => inner.map(Reply::Say),
             Self::Reply(inner) => inner.map(Reply::Reply),
             Self::Problem(inner) => inner.map(Reply::Problem),
+            Self::Action(inner) => inner.map(Reply::Action),
+            Self::Whisper(inner) => inner.map(Reply::Whisper),
         }
     }
 }
@@ -21,12 +27,18 @@ impl<T> Reply<T> {
             Self::Say(val) => Reply::Say(map(val)),
             Self::Reply(val) => Reply::Reply(map(val)),
             Self::Problem(val) => Reply::Problem(map(val)),
+            Self::Action(val) => Reply::Action(map(val)),
+            Self::Whisper(val) => Reply::Whisper(map(val)),
         }
     }
 
     pub const fn inner(&self) -> &T {
         match self {
-            Self::Say(val) | Self::Reply(val) | Self::Problem(val) => val,
+            Self::Say(val)
+            | Self::Reply(val)
+            | Self::Problem(val)
+            | Self::Action(val)
+            | Self::Whisper(val) => val,
         }
     }
 }
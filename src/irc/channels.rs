@@ -0,0 +1,253 @@
+use std::collections::BTreeSet;
+
+use crate::data::{Interest, InterestPath, SaveFile};
+
+/// The channels joined and parted at runtime
+///
+/// Parting a channel from `SHAKEN_TWITCH_CHANNELS` is remembered here, so it stays parted
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Channels {
+    #[serde(default)]
+    joined: BTreeSet<String>,
+    #[serde(default)]
+    parted: BTreeSet<String>,
+}
+
+impl Channels {
+    /// The `configured` channels, along with the ones joined and without the ones parted
+    fn resolve(&self, configured: &[String]) -> Vec<String> {
+        configured
+            .iter()
+            .chain(&self.joined)
+            .filter(|channel| !self.parted.contains(*channel))
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+}
+
+impl Interest for Channels {
+    fn module() -> InterestPath<&'static str> {
+        InterestPath::Nested("twitch")
+    }
+
+    fn file() -> &'static str {
+        "channels.yaml"
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ChannelUpdate {
+    Join(String),
+    Part(String),
+}
+
+/// The channels joined at runtime, these are kept across restarts
+#[derive(Clone)]
+pub struct ChannelControl {
+    channels: SaveFile<Channels>,
+    sender: flume::Sender<ChannelUpdate>,
+    recv: flume::Receiver<ChannelUpdate>,
+}
+
+impl ChannelControl {
+    /// Starts without any channels if there isn't a file yet
+    ///
+    /// Any other error is returned, so a file that cannot be read isn't overwritten
+    pub async fn load() -> anyhow::Result<Self> {
+        let channels = match crate::data::load_yaml::<Channels>().await {
+            Ok(channels) => channels,
+            Err(err) if is_not_found(&err) => {
+                log::info!("no saved channels, starting without any");
+                Channels::default()
+            }
+            Err(err) => return Err(err.context("cannot load the saved channels")),
+        };
+
+        Ok(Self::new(channels))
+    }
+
+    pub fn new(channels: Channels) -> Self {
+        let (sender, recv) = flume::unbounded();
+        Self {
+            channels: SaveFile::new(channels),
+            sender,
+            recv,
+        }
+    }
+
+    /// Channels are lowercase and start with a `#`
+    pub fn normalize(channel: &str) -> String {
+        let channel = channel.trim().to_ascii_lowercase();
+        match channel.starts_with('#') {
+            true => channel,
+            false => format!("#{channel}"),
+        }
+    }
+
+    /// Whether a normalized channel is a Twitch login, `#[a-z0-9_]{1,25}`
+    ///
+    /// Anything else could be more than one channel, like `#foo,#bar`
+    pub fn is_valid(channel: &str) -> bool {
+        let valid = |c: char| matches!(c, 'a'..='z' | '0'..='9' | '_');
+        match channel.strip_prefix('#') {
+            Some(name) => (1..=25).contains(&name.len()) && name.chars().all(valid),
+            None => false,
+        }
+    }
+
+    /// The channels to join, see [`Channels`]
+    pub async fn channels(&self, configured: &[String]) -> Vec<String> {
+        let mut channels = self.channels.get().await.resolve(configured);
+        channels.retain(|channel| {
+            let valid = Self::is_valid(channel);
+            if !valid {
+                log::warn!("not joining invalid channel: {}", channel.escape_debug());
+            }
+            valid
+        });
+        channels
+    }
+
+    /// Returns false if the channel was already joined
+    pub async fn join(&self, channel: &str) -> anyhow::Result<bool> {
+        let channel = Self::normalize(channel);
+        anyhow::ensure!(Self::is_valid(&channel), "invalid channel: {channel}");
+        let joined = self
+            .update(|channels| {
+                let parted = channels.parted.remove(&channel);
+                channels.joined.insert(channel.clone()) || parted
+            })
+            .await?;
+        if !joined {
+            return Ok(false);
+        }
+
+        let _ = self.sender.send(ChannelUpdate::Join(channel));
+        Ok(true)
+    }
+
+    pub async fn part(&self, channel: &str) -> anyhow::Result<()> {
+        let channel = Self::normalize(channel);
+        anyhow::ensure!(Self::is_valid(&channel), "invalid channel: {channel}");
+        self.update(|channels| {
+            let joined = channels.joined.remove(&channel);
+            channels.parted.insert(channel.clone()) || joined
+        })
+        .await?;
+
+        let _ = self.sender.send(ChannelUpdate::Part(channel));
+        Ok(())
+    }
+
+    /// Changes are only kept once they have been saved
+    ///
+    /// `update` returns whether it changed anything, there's nothing to save otherwise
+    async fn update(&self, update: impl FnOnce(&mut Channels) -> bool) -> anyhow::Result<bool> {
+        let mut channels = self.channels.get_mut().await;
+        let mut next = channels.clone();
+        if !update(&mut next) {
+            return Ok(false);
+        }
+
+        crate::data::save_yaml(&next).await?;
+        *channels = next;
+        Ok(true)
+    }
+
+    pub(super) async fn next_update(&self) -> Option<ChannelUpdate> {
+        self.recv.recv_async().await.ok()
+    }
+}
+
+fn is_not_found(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<std::io::Error>()
+        .filter(|err| err.kind() == std::io::ErrorKind::NotFound)
+        .is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set<const N: usize>(channels: [&str; N]) -> BTreeSet<String> {
+        channels.into_iter().map(String::from).collect()
+    }
+
+    #[test]
+    fn valid() {
+        for channel in ["#museun", "#shaken_bot", "#a", "#0123456789012345678901234"] {
+            assert!(ChannelControl::is_valid(channel), "{channel}");
+        }
+
+        for channel in [
+            "museun",
+            "#",
+            "#foo,#bar",
+            "#foo #bar",
+            "#foo\r\nPRIVMSG",
+            "#Museun",
+            "#museun-",
+            "#01234567890123456789012345",
+        ] {
+            assert!(!ChannelControl::is_valid(channel), "{channel}");
+        }
+
+        let normalized = ChannelControl::normalize("foo,#bar");
+        assert!(!ChannelControl::is_valid(&normalized));
+        assert!(ChannelControl::is_valid(&ChannelControl::normalize(
+            " MuseUn "
+        )));
+    }
+
+    #[test]
+    fn resolve() {
+        let configured = ["#museun".to_string(), "#shaken_bot".to_string()];
+        let channels = Channels {
+            joined: set(["#museun", "#other"]),
+            parted: set(["#shaken_bot"]),
+        };
+        assert_eq!(channels.resolve(&configured), ["#museun", "#other"]);
+        assert_eq!(Channels::default().resolve(&configured), configured);
+    }
+
+    #[test]
+    fn deserialize() {
+        let channels = Channels {
+            joined: set(["#other"]),
+            parted: set(["#museun"]),
+        };
+        let data = serde_yaml::to_string(&channels).unwrap();
+        assert_eq!(serde_yaml::from_str::<Channels>(&data).unwrap(), channels);
+
+        let joined = serde_yaml::from_str::<Channels>("joined: ['#museun']").unwrap();
+        assert_eq!(joined.joined, set(["#museun"]));
+        assert!(joined.parted.is_empty());
+
+        assert!(serde_yaml::from_str::<Channels>("- '#museun'").is_err());
+    }
+
+    #[tokio::test]
+    async fn load_errors() {
+        let dir = std::env::temp_dir().join(format!("shakey-channels-{}", std::process::id()));
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+
+        let err = crate::data::load_yaml_from::<Channels>(&dir)
+            .await
+            .unwrap_err();
+        assert!(is_not_found(&err));
+
+        let path = Channels::get_path(&dir);
+        tokio::fs::create_dir_all(path.parent().unwrap())
+            .await
+            .unwrap();
+        tokio::fs::write(&path, "joined: [[[").await.unwrap();
+        let err = crate::data::load_yaml_from::<Channels>(&dir)
+            .await
+            .unwrap_err();
+        assert!(!is_not_found(&err));
+
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+}
@@ -0,0 +1,266 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::EventKind;
+
+use super::{raw::Command, Tags};
+
+pub fn parse_event<'a>(command: &Command<'a>) -> Option<(&'a str, Tags, EventKind)> {
+    let (tags, channel) = match *command {
+        Command::UserNotice { tags, channel, .. }
+        | Command::ClearChat { tags, channel, .. }
+        | Command::ClearMsg { tags, channel, .. }
+        | Command::RoomState { tags, channel }
+        | Command::Notice {
+            tags,
+            target: channel,
+            ..
+        } => (tags.map(Tags::parse).unwrap_or_default(), channel),
+        _ => return None,
+    };
+
+    let kind = match *command {
+        Command::UserNotice { data, .. } => user_notice(&tags, data)?,
+        Command::ClearChat { user, .. } => clear_chat(&tags, user),
+        Command::ClearMsg { data, .. } => EventKind::MessageDeleted {
+            user: tags.get("login")?.into(),
+            id: tags.get("target-msg-id")?.into(),
+            data: data.into(),
+        },
+        Command::RoomState { .. } => room_state(&tags),
+        Command::Notice { data, .. } => EventKind::Notice {
+            kind: tags.get("msg-id").map(Arc::from),
+            data: data.into(),
+        },
+        _ => return None,
+    };
+
+    Some((channel, tags, kind))
+}
+
+fn user_notice(tags: &Tags, data: Option<&str>) -> Option<EventKind> {
+    let get = |key: &str| tags.get(key).map(Arc::from);
+    let user = || {
+        tags.display_name
+            .clone()
+            .or_else(|| get("login"))
+            .unwrap_or_else(|| Arc::from("anonymous"))
+    };
+    let plan = || get("msg-param-sub-plan").unwrap_or_else(|| Arc::from("1000"));
+
+    let kind = match tags.get("msg-id")? {
+        "sub" | "resub" => EventKind::Subscription {
+            user: user(),
+            months: tags.get_parsed("msg-param-cumulative-months").unwrap_or(1),
+            plan: plan(),
+            message: data.map(Arc::from),
+        },
+        kind @ ("subgift" | "anonsubgift") => EventKind::GiftSubscription {
+            gifter: (kind == "subgift").then(user),
+            recipient: get("msg-param-recipient-display-name")
+                .or_else(|| get("msg-param-recipient-user-name"))?,
+            months: tags.get_parsed("msg-param-months").unwrap_or(1),
+            plan: plan(),
+        },
+        "raid" => EventKind::Raid {
+            user: get("msg-param-displayName").unwrap_or_else(user),
+            viewers: tags.get_parsed("msg-param-viewerCount").unwrap_or(0),
+        },
+        kind => EventKind::UserNotice {
+            kind: kind.into(),
+            user: tags.display_name.clone().or_else(|| get("login")),
+            system_message: get("system-msg"),
+            message: data.map(Arc::from),
+        },
+    };
+    Some(kind)
+}
+
+fn clear_chat(tags: &Tags, user: Option<&str>) -> EventKind {
+    match (user, tags.get_parsed("ban-duration")) {
+        (Some(user), Some(secs)) => EventKind::Timeout {
+            user: user.into(),
+            duration: Duration::from_secs(secs),
+        },
+        (Some(user), None) => EventKind::Ban { user: user.into() },
+        (None, ..) => EventKind::ClearChat,
+    }
+}
+
+fn room_state(tags: &Tags) -> EventKind {
+    let flag = |key: &str| tags.get(key).map(|s| s == "1");
+    let minutes = |key: &str| {
+        tags.get_parsed::<i64>(key).map(|mins| {
+            u64::try_from(mins)
+                .ok()
+                .map(|mins| Duration::from_secs(mins * 60))
+        })
+    };
+
+    EventKind::RoomState {
+        emote_only: flag("emote-only"),
+        subs_only: flag("subs-only"),
+        followers_only: minutes("followers-only"),
+        slow: tags.get_parsed("slow").map(Duration::from_secs),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::irc::raw::parse_line;
+
+    fn event(line: &str) -> (String, EventKind) {
+        let line = parse_line(line).unwrap();
+        let (channel, _, kind) = parse_event(&line.command).expect("event");
+        (channel.to_string(), kind)
+    }
+
+    #[test]
+    fn sub() {
+        let (channel, kind) = event(
+            r"@badge-info=subscriber/1;badges=subscriber/0;color=#1E90FF;display-name=someone;emotes=;flags=;id=9a8b1a0c-4f8e-4a3e-9e2d-1f3b2c4d5e6f;login=someone;mod=0;msg-id=sub;msg-param-cumulative-months=1;msg-param-months=0;msg-param-multimonth-duration=1;msg-param-multimonth-tenure=0;msg-param-should-share-streak=0;msg-param-sub-plan-name=Channel\sSubscription\s(museun);msg-param-sub-plan=1000;msg-param-was-gifted=false;room-id=23196011;subscriber=1;system-msg=someone\ssubscribed\sat\sTier\s1.;tmi-sent-ts=1662000000000;user-id=42;user-type= :tmi.twitch.tv USERNOTICE #museun",
+        );
+        assert_eq!(channel, "#museun");
+        match kind {
+            EventKind::Subscription {
+                user,
+                months,
+                plan,
+                message,
+            } => {
+                assert_eq!(&*user, "someone");
+                assert_eq!(months, 1);
+                assert_eq!(&*plan, "1000");
+                assert_eq!(message, None);
+            }
+            kind => panic!("expected a subscription, got {kind:?}"),
+        }
+    }
+
+    #[test]
+    fn resub() {
+        let (_, kind) = event(
+            r"@badge-info=subscriber/15;badges=subscriber/12;color=;display-name=someone;emotes=;flags=;id=0d0e1f2a-3b4c-5d6e-7f80-91a2b3c4d5e6;login=someone;mod=0;msg-id=resub;msg-param-cumulative-months=15;msg-param-months=0;msg-param-should-share-streak=0;msg-param-sub-plan-name=Channel\sSubscription\s(museun);msg-param-sub-plan=Prime;room-id=23196011;subscriber=1;system-msg=someone\ssubscribed\swith\sPrime.\sThey've\ssubscribed\sfor\s15\smonths!;tmi-sent-ts=1662000000000;user-id=42;user-type= :tmi.twitch.tv USERNOTICE #museun :still here",
+        );
+        match kind {
+            EventKind::Subscription {
+                months,
+                plan,
+                message,
+                ..
+            } => {
+                assert_eq!(months, 15);
+                assert_eq!(&*plan, "Prime");
+                assert_eq!(message.as_deref(), Some("still here"));
+            }
+            kind => panic!("expected a subscription, got {kind:?}"),
+        }
+    }
+
+    #[test]
+    fn raid() {
+        let (_, kind) = event(
+            r"@badge-info=;badges=;color=#8A2BE2;display-name=Raider;emotes=;flags=;id=3d830f12-795c-447d-af3c-ea05e40fbddb;login=raider;mod=0;msg-id=raid;msg-param-displayName=Raider;msg-param-login=raider;msg-param-profileImageURL=https://static-cdn.jtvnw.net/jtv_user_pictures/raider-profile_image-70x70.png;msg-param-viewerCount=42;room-id=23196011;subscriber=0;system-msg=42\sraiders\sfrom\sRaider\shave\sjoined!;tmi-sent-ts=1662000000000;user-id=43;user-type= :tmi.twitch.tv USERNOTICE #museun",
+        );
+        match kind {
+            EventKind::Raid { user, viewers } => {
+                assert_eq!(&*user, "Raider");
+                assert_eq!(viewers, 42);
+            }
+            kind => panic!("expected a raid, got {kind:?}"),
+        }
+    }
+
+    #[test]
+    fn clear_chat() {
+        let (_, kind) = event(
+            "@ban-duration=600;room-id=23196011;target-user-id=42;tmi-sent-ts=1662000000000 \
+            :tmi.twitch.tv CLEARCHAT #museun :someone",
+        );
+        match kind {
+            EventKind::Timeout { user, duration } => {
+                assert_eq!(&*user, "someone");
+                assert_eq!(duration, Duration::from_secs(600));
+            }
+            kind => panic!("expected a timeout, got {kind:?}"),
+        }
+
+        let (_, kind) = event(
+            "@room-id=23196011;target-user-id=42;tmi-sent-ts=1662000000000 \
+            :tmi.twitch.tv CLEARCHAT #museun :someone",
+        );
+        assert!(matches!(kind, EventKind::Ban { user } if &*user == "someone"));
+
+        let (channel, kind) =
+            event("@room-id=23196011;tmi-sent-ts=1662000000000 :tmi.twitch.tv CLEARCHAT #museun");
+        assert_eq!(channel, "#museun");
+        assert!(matches!(kind, EventKind::ClearChat));
+    }
+
+    #[test]
+    fn clear_msg() {
+        let (_, kind) = event(
+            "@login=someone;room-id=;target-msg-id=b34ccfc7-4977-403a-8a94-33c6bac34fb8;\
+            tmi-sent-ts=1662000000000 :tmi.twitch.tv CLEARMSG #museun :this was deleted",
+        );
+        match kind {
+            EventKind::MessageDeleted { user, id, data } => {
+                assert_eq!(&*user, "someone");
+                assert_eq!(&*id, "b34ccfc7-4977-403a-8a94-33c6bac34fb8");
+                assert_eq!(&*data, "this was deleted");
+            }
+            kind => panic!("expected a deleted message, got {kind:?}"),
+        }
+    }
+
+    #[test]
+    fn room_state() {
+        let (_, kind) = event("@room-id=23196011;slow=10 :tmi.twitch.tv ROOMSTATE #museun");
+        match kind {
+            EventKind::RoomState {
+                emote_only,
+                subs_only,
+                followers_only,
+                slow,
+            } => {
+                assert_eq!(emote_only, None);
+                assert_eq!(subs_only, None);
+                assert_eq!(followers_only, None);
+                assert_eq!(slow, Some(Duration::from_secs(10)));
+            }
+            kind => panic!("expected a room state, got {kind:?}"),
+        }
+
+        let (_, kind) = event(
+            "@emote-only=0;followers-only=-1;r9k=0;room-id=23196011;slow=0;subs-only=1 \
+            :tmi.twitch.tv ROOMSTATE #museun",
+        );
+        match kind {
+            EventKind::RoomState {
+                emote_only,
+                subs_only,
+                followers_only,
+                slow,
+            } => {
+                assert_eq!(emote_only, Some(false));
+                assert_eq!(subs_only, Some(true));
+                assert_eq!(followers_only, Some(None));
+                assert_eq!(slow, Some(Duration::ZERO));
+            }
+            kind => panic!("expected a room state, got {kind:?}"),
+        }
+    }
+
+    #[test]
+    fn notice() {
+        let (_, kind) =
+            event("@msg-id=slow_on :tmi.twitch.tv NOTICE #museun :This room is now in slow mode.");
+        match kind {
+            EventKind::Notice { kind, data } => {
+                assert_eq!(kind.as_deref(), Some("slow_on"));
+                assert_eq!(&*data, "This room is now in slow mode.");
+            }
+            kind => panic!("expected a notice, got {kind:?}"),
+        }
+    }
+}
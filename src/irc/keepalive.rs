@@ -0,0 +1,113 @@
+use std::time::{Duration, Instant};
+
+pub enum Check {
+    Alive,
+    SendPing,
+    TimedOut,
+}
+
+/// Tracks whether the connection is still alive
+///
+/// After being idle for a while we send a PING, and if nothing arrives before
+/// the deadline the connection is considered dead
+pub struct Keepalive {
+    idle: Duration,
+    deadline: Duration,
+    last_seen: Instant,
+    ping_sent: Option<Instant>,
+}
+
+impl Keepalive {
+    pub const IDLE: Duration = Duration::from_secs(120);
+    pub const DEADLINE: Duration = Duration::from_secs(15);
+
+    pub fn new(now: Instant) -> Self {
+        Self::with_timeouts(Self::IDLE, Self::DEADLINE, now)
+    }
+
+    pub fn with_timeouts(idle: Duration, deadline: Duration, now: Instant) -> Self {
+        Self {
+            idle,
+            deadline,
+            last_seen: now,
+            ping_sent: None,
+        }
+    }
+
+    /// Anything read from the connection counts as activity
+    pub fn seen(&mut self, now: Instant) {
+        self.last_seen = now;
+        self.ping_sent.take();
+    }
+
+    pub fn check(&mut self, now: Instant) -> Check {
+        match self.ping_sent {
+            Some(sent) if now >= sent + self.deadline => Check::TimedOut,
+            Some(..) => Check::Alive,
+            None if now >= self.last_seen + self.idle => {
+                self.ping_sent.replace(now);
+                Check::SendPing
+            }
+            None => Check::Alive,
+        }
+    }
+
+    /// How long until `check` should be called again
+    pub fn next_check(&self, now: Instant) -> Duration {
+        let next = match self.ping_sent {
+            Some(sent) => sent + self.deadline,
+            None => self.last_seen + self.idle,
+        };
+        next.saturating_duration_since(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDLE: Duration = Duration::from_secs(10);
+    const DEADLINE: Duration = Duration::from_secs(2);
+
+    #[test]
+    fn ping_after_idle() {
+        let start = Instant::now();
+        let mut keepalive = Keepalive::with_timeouts(IDLE, DEADLINE, start);
+
+        assert!(matches!(keepalive.check(start), Check::Alive));
+        assert_eq!(keepalive.next_check(start), IDLE);
+
+        let now = start + Duration::from_secs(5);
+        keepalive.seen(now);
+        assert!(matches!(
+            keepalive.check(now + Duration::from_secs(9)),
+            Check::Alive
+        ));
+
+        let now = now + IDLE;
+        assert!(matches!(keepalive.check(now), Check::SendPing));
+        // only one ping is sent while waiting for the deadline
+        assert!(matches!(keepalive.check(now), Check::Alive));
+        assert_eq!(keepalive.next_check(now), DEADLINE);
+
+        // anything arriving answers the ping
+        keepalive.seen(now + Duration::from_secs(1));
+        assert!(matches!(keepalive.check(now + DEADLINE), Check::Alive));
+        assert_eq!(keepalive.next_check(now + Duration::from_secs(1)), IDLE);
+    }
+
+    #[test]
+    fn timeout_after_unanswered_ping() {
+        let start = Instant::now();
+        let mut keepalive = Keepalive::with_timeouts(IDLE, DEADLINE, start);
+
+        let now = start + IDLE;
+        assert!(matches!(keepalive.check(now), Check::SendPing));
+        assert!(matches!(
+            keepalive.check(now + Duration::from_secs(1)),
+            Check::Alive
+        ));
+        assert!(matches!(keepalive.check(now + DEADLINE), Check::TimedOut));
+        assert_eq!(keepalive.next_check(now + DEADLINE), Duration::ZERO);
+    }
+}
@@ -1,10 +1,10 @@
 use std::sync::Arc;
 
-use super::raw::Command;
+use super::{raw::Command, Tags};
 
 #[derive(Clone)]
 pub struct Message {
-    pub tags: Option<Arc<str>>,
+    pub tags: Arc<Tags>,
     pub sender: Arc<str>,
     pub target: Arc<str>,
     pub data: Arc<str>,
@@ -24,13 +24,10 @@ impl std::fmt::Debug for Message {
 
 impl Message {
     pub fn badges_iter(&self) -> impl Iterator<Item = (&'_ str, &'_ str)> + '_ {
-        self.tags.iter().flat_map(|tags| {
-            tags.split(';')
-                .flat_map(|val| val.split_once('='))
-                .filter_map(|(k, v)| (k == "badges").then_some(v))
-                .flat_map(|v| v.split(','))
-                .flat_map(|v| v.split_once('/'))
-        })
+        self.tags
+            .badges
+            .iter()
+            .map(|badge| (&*badge.name, &*badge.version))
     }
 
     pub(crate) fn new(command: Command<'_>) -> Self {
@@ -44,7 +41,7 @@ impl Message {
         } = command
         {
             return Self {
-                tags: tags.map(|s| s.into()),
+                tags: Arc::new(tags.map(Tags::parse).unwrap_or_default()),
                 sender: sender.into(),
                 target: target.into(),
                 data: data.into(),
@@ -0,0 +1,467 @@
+//! A small Twitch IRC server for driving [`super::run`] in tests
+use std::time::Duration;
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufStream},
+    net::{TcpListener, TcpStream},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct MockServer {
+    listener: TcpListener,
+}
+
+impl MockServer {
+    pub async fn bind() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        Self { listener }
+    }
+
+    pub fn address(&self) -> String {
+        self.listener.local_addr().unwrap().to_string()
+    }
+
+    /// Accepts the next connection and completes its registration
+    pub async fn accept(&self) -> Connection {
+        let (stream, _) = tokio::time::timeout(TIMEOUT, self.listener.accept())
+            .await
+            .expect("bot should connect")
+            .unwrap();
+
+        let mut conn = Connection {
+            stream: BufStream::new(stream),
+            name: String::new(),
+            pass: None,
+            caps: vec![],
+            channels: vec![],
+            next_id: 0,
+        };
+        conn.register().await;
+        conn
+    }
+}
+
+pub struct Connection {
+    stream: BufStream<TcpStream>,
+    pub name: String,
+    pub pass: Option<String>,
+    pub caps: Vec<String>,
+    pub channels: Vec<String>,
+    next_id: usize,
+}
+
+impl Connection {
+    async fn register(&mut self) {
+        loop {
+            let line = self.read_raw().await.expect("bot disconnected");
+            match line.split_once(' ') {
+                Some(("CAP", cap)) => {
+                    let cap = cap
+                        .strip_prefix("REQ :")
+                        .expect("only CAP REQ is supported");
+                    self.caps.push(cap.to_string());
+                    self.send(&format!(":tmi.twitch.tv CAP * ACK :{cap}")).await;
+                }
+                Some(("PASS", pass)) => self.pass = Some(pass.to_string()),
+                Some(("NICK", name)) => {
+                    self.name = name.to_string();
+                    break;
+                }
+                _ => panic!("unexpected line during registration: {line}"),
+            }
+        }
+
+        let name = self.name.clone();
+        self.send(&format!(":tmi.twitch.tv 001 {name} :Welcome, GLHF!"))
+            .await;
+        self.send(&format!(":tmi.twitch.tv 376 {name} :>")).await;
+
+        // anonymous logins aren't sent this
+        if name.starts_with("justinfan") {
+            return;
+        }
+        self.send(&format!(
+            "@badge-info=;badges=;color=;display-name={name};emote-sets=0;user-id=1234;user-type= \
+            :tmi.twitch.tv GLOBALUSERSTATE"
+        ))
+        .await;
+    }
+
+    pub async fn send(&mut self, line: &str) {
+        self.stream.write_all(line.as_bytes()).await.unwrap();
+        self.stream.write_all(b"\r\n").await.unwrap();
+        self.stream.flush().await.unwrap();
+    }
+
+    /// Sends a chat message from `user` with the badges (e.g. `broadcaster/1`), returning its id
+    pub async fn privmsg(&mut self, channel: &str, user: &str, badges: &str, data: &str) -> String {
+        self.next_id += 1;
+        let id = format!("msg-{}", self.next_id);
+        self.send(&format!(
+            "@badge-info=;badges={badges};color=#FF0000;display-name={user};emotes=;first-msg=0;\
+            id={id};mod=0;room-id=1;subscriber=0;tmi-sent-ts=0;turbo=0;user-id=42;user-type= \
+            :{user}!{user}@{user}.tmi.twitch.tv PRIVMSG {channel} :{data}"
+        ))
+        .await;
+        id
+    }
+
+    pub async fn ping(&mut self, token: &str) {
+        self.send(&format!("PING :{token}")).await
+    }
+
+    pub async fn reconnect(&mut self) {
+        self.send(":tmi.twitch.tv RECONNECT").await
+    }
+
+    /// Reads the next line from the bot, answering any JOIN, PART or PING along the way
+    pub async fn read_line(&mut self) -> String {
+        let line = self.read_raw().await.expect("bot disconnected");
+        let name = self.name.clone();
+
+        match line.split_once(' ') {
+            Some(("JOIN", channel)) => {
+                self.channels.push(channel.to_string());
+                self.send(&format!(
+                    ":{name}!{name}@{name}.tmi.twitch.tv JOIN {channel}"
+                ))
+                .await;
+                self.send(&format!(
+                    "@badge-info=;badges=;color=;display-name={name};emote-sets=0;mod=0;\
+                    subscriber=0;user-type= :tmi.twitch.tv USERSTATE {channel}"
+                ))
+                .await;
+                self.send(&format!(
+                    "@emote-only=0;followers-only=-1;r9k=0;room-id=1;slow=0;subs-only=0 \
+                    :tmi.twitch.tv ROOMSTATE {channel}"
+                ))
+                .await;
+            }
+            Some(("PART", channel)) => {
+                self.channels.retain(|c| c != channel);
+                self.send(&format!(
+                    ":{name}!{name}@{name}.tmi.twitch.tv PART {channel}"
+                ))
+                .await;
+            }
+            Some(("PING", token)) => {
+                self.send(&format!(":tmi.twitch.tv PONG tmi.twitch.tv {token}"))
+                    .await;
+            }
+            _ => {}
+        }
+
+        line
+    }
+
+    pub async fn wait_for_join(&mut self, channel: &str) {
+        while !self.channels.iter().any(|c| c == channel) {
+            self.read_line().await;
+        }
+    }
+
+    /// Skips everything up to the next PRIVMSG, returning the whole line
+    pub async fn next_privmsg(&mut self) -> String {
+        loop {
+            let line = self.read_line().await;
+            let command = match line.strip_prefix('@') {
+                Some(line) => line.split_once(' ').map_or("", |(_, tail)| tail),
+                None => &*line,
+            };
+            if command.starts_with("PRIVMSG ") {
+                return line;
+            }
+        }
+    }
+
+    /// Waits for the bot to close the connection
+    pub async fn closed(&mut self) {
+        while self.read_raw().await.is_some() {}
+    }
+
+    async fn read_raw(&mut self) -> Option<String> {
+        let mut buf = String::new();
+        let n = tokio::time::timeout(TIMEOUT, self.stream.read_line(&mut buf))
+            .await
+            .expect("bot should write a line")
+            .ok()?;
+        (n > 0).then(|| buf.trim_end_matches("\r\n").to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::{sync::OnceCell, task::JoinHandle};
+
+    use super::*;
+    use crate::{
+        global::GlobalItem,
+        handler::{Bindable, Components, SharedCallable},
+        irc::{self, ChannelControl, Channels, Login, Settings, Sink},
+        modules::{Help, UserDefined},
+        supervisor::Ready,
+        Commands, Templates,
+    };
+
+    async fn handlers() -> Vec<SharedCallable> {
+        static HANDLERS: OnceCell<Vec<SharedCallable>> = OnceCell::const_new();
+
+        async fn init() -> Vec<SharedCallable> {
+            let dir = std::env::temp_dir().join(format!("shakey-mock-{}", std::process::id()));
+            tokio::fs::create_dir_all(dir.join("user_defined"))
+                .await
+                .unwrap();
+            tokio::fs::write(dir.join("user_defined").join("commands.yaml"), "{}")
+                .await
+                .unwrap();
+            std::env::set_var("SHAKEN_DATA_DIR", &dir);
+
+            let commands = serde_yaml::from_str(include_str!("../../commands.yaml")).unwrap();
+            Commands::get_static().initialize(Arc::new(commands));
+            let templates = serde_yaml::from_str(include_str!("../../templates.yaml")).unwrap();
+            Templates::get_static().initialize(Arc::new(templates));
+            crate::bind_system_errors().unwrap();
+
+            let components = Components::default();
+            vec![
+                Help::bind(&components).await.unwrap().into_callable(),
+                UserDefined::bind(&components)
+                    .await
+                    .unwrap()
+                    .into_callable(),
+            ]
+        }
+
+        HANDLERS.get_or_init(init).await.clone()
+    }
+
+    async fn start() -> (MockServer, JoinHandle<anyhow::Result<()>>) {
+        start_with(Login::User {
+            name: "shakey".into(),
+            oauth: "oauth:hunter2".into(),
+        })
+        .await
+    }
+
+    /// Runs the bot, reconnecting when asked to
+    async fn start_with(login: Login) -> (MockServer, JoinHandle<anyhow::Result<()>>) {
+        let server = MockServer::bind().await;
+        let settings = Settings {
+            address: server.address(),
+            channels: vec!["#test".into()],
+            login,
+        };
+
+        let handlers = handlers().await;
+        let control = ChannelControl::new(Channels::default());
+        let bot = tokio::spawn(async move {
+            loop {
+                let ready = Ready::default();
+                match irc::run(&settings, handlers.clone(), control.clone(), ready).await {
+                    Err(err) if err.is::<irc::errors::Connection>() => continue,
+                    res => break res,
+                }
+            }
+        });
+
+        (server, bot)
+    }
+
+    #[tokio::test]
+    async fn registration() {
+        let (server, bot) = start().await;
+        let mut conn = server.accept().await;
+        conn.wait_for_join("#test").await;
+
+        assert_eq!(conn.name, "shakey");
+        assert_eq!(conn.pass.as_deref(), Some("oauth:hunter2"));
+        for cap in [
+            "twitch.tv/membership",
+            "twitch.tv/tags",
+            "twitch.tv/commands",
+        ] {
+            assert!(conn.caps.iter().any(|c| c == cap), "missing {cap}");
+        }
+
+        conn.ping("tmi.twitch.tv").await;
+        assert_eq!(conn.read_line().await, "PONG tmi.twitch.tv");
+
+        bot.abort();
+    }
+
+    #[tokio::test]
+    async fn user_defined_commands() {
+        let (server, bot) = start().await;
+        let mut conn = server.accept().await;
+        conn.wait_for_join("#test").await;
+
+        let id = conn
+            .privmsg("#test", "someone", "moderator/1", "!add !greet hello there")
+            .await;
+        assert_eq!(
+            conn.next_privmsg().await,
+            format!("@reply-parent-msg-id={id} PRIVMSG #test :added !greet -> hello there")
+        );
+
+        let id = conn.privmsg("#test", "someone", "", "!greet").await;
+        assert_eq!(
+            conn.next_privmsg().await,
+            format!("@reply-parent-msg-id={id} PRIVMSG #test :hello there")
+        );
+
+        conn.privmsg("#test", "someone", "", "!help !greet").await;
+        assert_eq!(
+            conn.next_privmsg().await,
+            "PRIVMSG #test :I don't know what !greet does"
+        );
+
+        let id = conn.privmsg("#test", "someone", "", "!remove !greet").await;
+        assert_eq!(
+            conn.next_privmsg().await,
+            format!(
+                "@reply-parent-msg-id={id} PRIVMSG #test :that requires you to be a moderator or higher"
+            )
+        );
+
+        bot.abort();
+    }
+
+    #[tokio::test]
+    async fn unprivileged_add() {
+        let (server, bot) = start().await;
+        let mut conn = server.accept().await;
+        conn.wait_for_join("#test").await;
+
+        for badges in ["", "subscriber/12", "vip/1"] {
+            let id = conn
+                .privmsg("#test", "someone", badges, "!add !sneaky not allowed")
+                .await;
+            assert_eq!(
+                conn.next_privmsg().await,
+                format!(
+                    "@reply-parent-msg-id={id} PRIVMSG #test :that requires you to be a moderator or higher"
+                )
+            );
+        }
+
+        conn.privmsg("#test", "someone", "", "!help !sneaky").await;
+        assert_eq!(
+            conn.next_privmsg().await,
+            "PRIVMSG #test :I don't know what !sneaky does"
+        );
+
+        bot.abort();
+    }
+
+    #[tokio::test]
+    async fn help_is_said() {
+        let (server, bot) = start().await;
+        let mut conn = server.accept().await;
+        conn.wait_for_join("#test").await;
+
+        // the list is too long for chat, but whispers can't be sent here
+        conn.privmsg("#test", "someone", "", "!help").await;
+        let line = conn.next_privmsg().await;
+        assert!(line.starts_with("PRIVMSG #test :!"), "{line}");
+
+        bot.abort();
+    }
+
+    #[tokio::test]
+    async fn command_injection() {
+        let (server, bot) = start().await;
+        let mut conn = server.accept().await;
+        conn.wait_for_join("#test").await;
+
+        let id = conn
+            .privmsg(
+                "#test",
+                "someone",
+                "moderator/1",
+                "!add !evil hi\rPRIVMSG #other :pwned\0",
+            )
+            .await;
+        assert_eq!(
+            conn.next_privmsg().await,
+            format!(
+                "@reply-parent-msg-id={id} PRIVMSG #test :added !evil -> hi PRIVMSG #other :pwned"
+            )
+        );
+
+        let id = conn.privmsg("#test", "someone", "", "!evil").await;
+        assert_eq!(
+            conn.next_privmsg().await,
+            format!("@reply-parent-msg-id={id} PRIVMSG #test :hi PRIVMSG #other :pwned")
+        );
+
+        bot.abort();
+    }
+
+    #[tokio::test]
+    async fn reconnect() {
+        let (server, bot) = start().await;
+        let mut conn = server.accept().await;
+        conn.wait_for_join("#test").await;
+
+        conn.reconnect().await;
+        conn.closed().await;
+
+        let mut conn = server.accept().await;
+        conn.wait_for_join("#test").await;
+
+        let id = conn
+            .privmsg(
+                "#test",
+                "someone",
+                "broadcaster/1",
+                "!add !again still here",
+            )
+            .await;
+        assert_eq!(
+            conn.next_privmsg().await,
+            format!("@reply-parent-msg-id={id} PRIVMSG #test :added !again -> still here")
+        );
+
+        bot.abort();
+    }
+
+    #[tokio::test]
+    async fn read_only() {
+        let path = std::env::temp_dir().join(format!("shakey-sink-{}.log", std::process::id()));
+        let _ = tokio::fs::remove_file(&path).await;
+
+        let (server, bot) = start_with(Login::Anonymous {
+            sink: Sink::File(path.clone()),
+        })
+        .await;
+        let mut conn = server.accept().await;
+        conn.wait_for_join("#test").await;
+
+        assert!(conn.name.starts_with("justinfan"));
+        assert!(conn.pass.is_none());
+
+        let id = conn
+            .privmsg("#test", "someone", "moderator/1", "!add !quiet not in chat")
+            .await;
+
+        let expected =
+            format!("@reply-parent-msg-id={id} PRIVMSG #test :added !quiet -> not in chat\n");
+        tokio::time::timeout(TIMEOUT, async {
+            while tokio::fs::read_to_string(&path).await.unwrap_or_default() != expected {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("reply should be written to the sink");
+
+        // the reply was never written to the socket
+        conn.ping("tmi.twitch.tv").await;
+        assert_eq!(conn.read_line().await, "PONG tmi.twitch.tv");
+
+        bot.abort();
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+}
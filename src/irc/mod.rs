@@ -1,86 +1,275 @@
-use tokio::io::BufStream;
+use std::time::Instant;
+
+use tokio::io::{BufReader, BufStream};
 
 use crate::{
-    env::EnvVar,
+    env::EnvVar as _,
     ext::{Either, FutureExt},
-    handler::SharedCallable,
+    handler::{Incoming, SharedCallable},
+    platform::{dispatch, responses, Platform as _},
+    supervisor::Ready,
+    EventKind,
 };
 
 mod proto;
-use proto::{connect, join, read_line, read_responses, wait_for_ready, write_raw};
+use proto::{connect, handle_line, join, part, wait_for_ready, write_raw, Lines};
 
 mod message;
 pub use message::Message;
 
+mod platform;
+pub use platform::{Outgoing, Twitch};
+
+mod tags;
+pub use tags::{Badge, Color, Emote, ReplyParent, Tags};
+
 mod raw;
 use raw::Command;
 
+mod event;
+use event::parse_event;
+
+mod keepalive;
+use keepalive::{Check, Keepalive};
+
+mod transport;
+pub use transport::{BoxedStream, Stream, Transport};
+
+mod queue;
+pub use queue::{Limits, Pending, Priority, SendQueue};
+
+mod channels;
+pub use channels::{ChannelControl, ChannelUpdate, Channels};
+
+mod sink;
+pub use sink::Sink;
+
+#[cfg(test)]
+mod mock;
+
 pub mod errors {
     pub use super::proto::{Connection, Eof, Timeout};
 }
 
-pub async fn run(handlers: Vec<SharedCallable>) -> anyhow::Result<()> {
-    let channels = crate::env::SHAKEN_TWITCH_CHANNELS::get()?;
-    let channels = channels.split(',').collect::<Vec<_>>();
+/// How to connect to Twitch
+#[derive(Clone)]
+pub struct Settings {
+    pub address: String,
+    pub channels: Vec<String>,
+    pub login: Login,
+}
+
+#[derive(Clone)]
+pub enum Login {
+    User {
+        name: String,
+        oauth: String,
+    },
+    /// Connects as `justinfanNNNN`, replies are sent to the sink rather than to chat
+    Anonymous {
+        sink: Sink,
+    },
+}
+
+impl Settings {
+    pub fn load(config: &crate::config::TwitchConfig) -> anyhow::Result<Self> {
+        let login = match config.read_only {
+            true => Login::Anonymous {
+                sink: config.sink.clone().map_or(Sink::Log, Sink::File),
+            },
+            false => Login::User {
+                name: crate::env::SHAKEN_TWITCH_NAME::get()?,
+                oauth: crate::env::SHAKEN_TWITCH_OAUTH_TOKEN::get()?,
+            },
+        };
+
+        let channels = crate::env::SHAKEN_TWITCH_CHANNELS::get()?
+            .split(',')
+            .filter(|channel| !channel.trim().is_empty())
+            .map(ChannelControl::normalize)
+            .collect::<Vec<_>>();
+        if let Some(channel) = channels.iter().find(|c| !ChannelControl::is_valid(c)) {
+            anyhow::bail!("invalid channel in SHAKEN_TWITCH_CHANNELS: {channel}")
+        }
+
+        Ok(Self {
+            address: crate::env::SHAKEN_TWITCH_ADDRESS::get()?,
+            channels,
+            login,
+        })
+    }
+}
+
+pub async fn run(
+    settings: &Settings,
+    handlers: Vec<SharedCallable>,
+    control: ChannelControl,
+    ready: Ready,
+) -> anyhow::Result<()> {
+    let channels = control.channels(&settings.channels).await;
     anyhow::ensure!(!channels.is_empty(), "channels cannot be empty");
 
-    let stream = connect(
-        &crate::env::SHAKEN_TWITCH_ADDRESS::get()?,
-        &crate::env::SHAKEN_TWITCH_NAME::get()?,
-        &crate::env::SHAKEN_TWITCH_OAUTH_TOKEN::get()?,
-    )
-    .await?;
+    let (name, oauth, sink) = match &settings.login {
+        Login::User { name, oauth } => (name.clone(), Some(&**oauth), None),
+        Login::Anonymous { sink } => {
+            let name = format!("justinfan{}", fastrand::u16(1000..10000));
+            log::info!("connecting in read-only mode");
+            (name, None, Some(sink))
+        }
+    };
+
+    let stream = connect(&settings.address, &name, oauth).await?;
 
     let mut stream = BufStream::new(stream);
 
     let mut buf = String::with_capacity(1024);
-    let identity = wait_for_ready(&mut buf, &mut stream).await?;
-    log::info!(
-        "connected, our identity: {} | id: {}",
-        identity.display_name,
-        identity.user_id
-    );
-
-    for channel in channels {
+    let identity = wait_for_ready(&mut buf, &mut stream, &name, oauth.is_none()).await?;
+    match identity.user_id {
+        Some(id) => log::info!(
+            "connected, our identity: {} | id: {id}",
+            identity.display_name
+        ),
+        None => log::info!("connected anonymously as: {}", identity.display_name),
+    }
+
+    // the reading is done in its own task so a partially read line isn't lost
+    // when something else is ready first
+    let (read, mut stream) = tokio::io::split(stream);
+    let mut lines = Lines::spawn(BufReader::new(read));
+
+    for channel in &channels {
         log::info!("joining: {channel}");
         join(channel, &mut stream).await?;
     }
+    ready.ready();
 
     let (write_tx, mut write_rx) = tokio::sync::mpsc::channel(32);
+    let mut queue = SendQueue::new(Instant::now());
+
+    let mut keepalive = Keepalive::new(Instant::now());
 
     loop {
-        match read_line(&mut buf, &mut stream)
-            .select(write_rx.recv())
-            .await
-        {
+        match keepalive.check(Instant::now()) {
+            Check::Alive => {}
+            Check::SendPing => write_raw("PING :shakey\r\n", &mut stream).await?,
+            Check::TimedOut => {
+                log::warn!("no response from the server, timing out");
+                break Err(errors::Timeout.into());
+            }
+        }
+
+        while let Some(Pending { data, .. }) = queue.pop(Instant::now()) {
+            write_raw(&data, &mut stream).await?;
+        }
+
+        let now = Instant::now();
+        let wait = keepalive.next_check(now);
+        let wait = queue.next_ready(now).map_or(wait, |next| next.min(wait));
+        let next = write_rx
+            .recv()
+            .select(control.next_update().select(tokio::time::sleep(wait)));
+
+        match lines.next().select(next).await {
             Either::Left(Err(err)) => break Err(err),
 
-            Either::Left(Ok(msg)) => {
-                if let msg @ Command::Privmsg {
-                    ref sender,
-                    ref target,
-                    ref data,
-                    ..
-                } = msg.command
-                {
-                    log::debug!("[{}] {}: {}", target, sender, data);
-
-                    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
-                    let irc_msg = Message::new(msg);
-                    let msg = crate::Message::twitch(irc_msg, tx);
-                    for handler in &handlers {
-                        // outcome is always () here
-                        (handler)(msg.clone());
+            Either::Left(Ok(line)) => {
+                keepalive.seen(Instant::now());
+                let msg = match handle_line(&line, &mut stream).await {
+                    Ok(msg) => msg,
+                    Err(err) => break Err(err),
+                };
+                match msg.command {
+                    msg @ Command::Privmsg {
+                        ref sender,
+                        ref target,
+                        ref data,
+                        ..
+                    } => {
+                        log::debug!("[{}] {}: {}", target, sender, data);
+
+                        let msg = Message::new(msg);
+                        let out = Outgoing {
+                            target: msg.target.clone(),
+                            sender: Some(msg.sender.clone()),
+                            parent_id: msg.tags.id.clone(),
+                            out: write_tx.clone(),
+                        };
+                        dispatch::<Twitch>(msg, &handlers, out);
+                    }
+
+                    Command::UserState { tags, channel } => {
+                        let tags = tags.map(Tags::parse).unwrap_or_default();
+                        let moderator =
+                            tags.get("mod") == Some("1") || tags.has_badge("broadcaster");
+                        let vip = tags.get("vip") == Some("1") || tags.has_badge("vip");
+                        log::debug!("[{channel}] moderator: {moderator}, vip: {vip}");
+                        queue.set_privileges(channel, moderator, vip);
+                    }
+
+                    Command::Pong { token } => log::trace!("got pong: {token}"),
+
+                    Command::Reconnect => {
+                        log::info!("server has asked us to reconnect");
+                        break Err(errors::Connection.into());
                     }
 
-                    tokio::spawn(read_responses(msg, rx, write_tx.clone()));
+                    command => {
+                        let (channel, tags, kind) = match parse_event(&command) {
+                            Some(event) => event,
+                            None => continue,
+                        };
+                        log::debug!("[{channel}] {kind:?}");
+
+                        if let EventKind::RoomState {
+                            slow: Some(slow), ..
+                        } = kind
+                        {
+                            queue.set_slow_mode(channel, slow);
+                        }
+
+                        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+                        let event = crate::Event::twitch(kind, channel, tags, tx);
+                        for handler in &handlers {
+                            (handler)(Incoming::Event(event.clone()));
+                        }
+
+                        let out = Outgoing {
+                            target: event.target.clone(),
+                            sender: None,
+                            parent_id: None,
+                            out: write_tx.clone(),
+                        };
+                        tokio::spawn(Twitch::deliver(out, responses(rx)));
+                    }
                 }
             }
 
-            Either::Right(Some(data)) => {
-                write_raw(&data, &mut stream).await?;
+            Either::Right(Either::Left(Some(pending))) => {
+                if let Some(sink) = sink {
+                    if let Err(err) = sink.write(&pending).await {
+                        log::warn!("cannot write to the sink: {err}");
+                    }
+                    continue;
+                }
+
+                if let Some(Pending { data, .. }) = queue.push(pending) {
+                    log::warn!("send queue is full, dropping: {}", data.escape_debug());
+                }
             }
 
+            Either::Right(Either::Right(Either::Left(Some(update)))) => match update {
+                ChannelUpdate::Join(channel) => {
+                    log::info!("joining: {channel}");
+                    join(&channel, &mut stream).await?
+                }
+                ChannelUpdate::Part(channel) => {
+                    log::info!("leaving: {channel}");
+                    part(&channel, &mut stream).await?
+                }
+            },
+
+            Either::Right(Either::Right(Either::Right(()))) => {}
+
             _ => break Ok(()),
         }
     }
@@ -0,0 +1,275 @@
+use std::sync::Arc;
+
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    global::GlobalItem,
+    handler::{Permission, Reply, SharedCallable},
+    message::SenderPriv,
+    platform::{Parts, Platform, Responses},
+    split::Splitter,
+    supervisor::Ready,
+    templates::Variant,
+    Replier, Response, Templates,
+};
+
+use super::{tags::escape, ChannelControl, Message, Pending, Priority, Settings};
+
+/// Twitch chat, over IRC
+#[derive(Clone)]
+pub struct Twitch {
+    pub settings: Settings,
+    pub control: ChannelControl,
+}
+
+/// Where the replies to a Twitch message go
+///
+/// Replies are threaded to `parent_id` when it is known, otherwise they are prefixed with the `sender`
+pub struct Outgoing {
+    pub(super) target: Arc<str>,
+    pub(super) sender: Option<Arc<str>>,
+    pub(super) parent_id: Option<Arc<str>>,
+    pub(super) out: Sender<Pending>,
+}
+
+impl<R: Replier> crate::Message<R> {
+    pub fn is_twitch(&self) -> bool {
+        self.source::<Twitch>().is_some()
+    }
+
+    /// The Twitch message, along with its tags
+    pub fn as_twitch(&self) -> Option<Message> {
+        self.source::<Twitch>()
+    }
+}
+
+#[async_trait::async_trait]
+impl Platform for Twitch {
+    type Message = Message;
+    type Rendered = String;
+    type Outgoing = Outgoing;
+
+    const NAME: &'static str = "twitch";
+    const VARIANT: Variant = Variant::Default;
+    // these need a user token with the helix api
+    const WHISPERS: bool = false;
+
+    async fn run(
+        &self,
+        handlers: Vec<(String, SharedCallable)>,
+        ready: Ready,
+    ) -> anyhow::Result<()> {
+        let handlers = handlers.into_iter().map(|(_, handler)| handler).collect();
+        super::run(&self.settings, handlers, self.control.clone(), ready).await
+    }
+
+    fn parts(msg: &Self::Message) -> Parts {
+        Parts {
+            sender: msg.sender.clone(),
+            target: msg.target.clone(),
+            data: msg.data.clone(),
+            timestamp: msg.timestamp,
+            tags: Some(msg.tags.clone()),
+            expects_reply: false,
+        }
+    }
+
+    fn sender_priv(msg: &Self::Message) -> SenderPriv {
+        msg.badges_iter()
+            .filter_map(|badge| match badge {
+                ("broadcaster", "1") => Some(SenderPriv::Admin),
+                ("moderator", "1") => Some(SenderPriv::Moderator),
+                ("vip", "1") => Some(SenderPriv::Vip),
+                ("subscriber" | "founder", _) => Some(SenderPriv::Subscriber),
+                _ => None,
+            })
+            .max_by_key(|&priv_| Permission::from(priv_))
+            .unwrap_or_default()
+    }
+
+    fn render(resp: Box<dyn Response>) -> Option<Self::Rendered> {
+        // \x01 is only allowed as the framing of an action
+        Templates::get()
+            .render(&resp, Self::VARIANT)
+            .map(|resp| resp.replace('\x01', ""))
+    }
+
+    async fn deliver(out: Self::Outgoing, mut responses: Responses<Self>) {
+        let Outgoing {
+            target,
+            sender,
+            parent_id,
+            out,
+        } = out;
+
+        while let Some(resp) = responses.next().await {
+            // sending it to the chat would make it public
+            if let Reply::Whisper(..) = resp {
+                log::warn!("[{target}] dropping a whisper, they cannot be sent here");
+                continue;
+            }
+
+            let priority = Priority::from(&resp);
+
+            for data in frame(resp, &target, sender.as_deref(), parent_id.as_deref()) {
+                let pending = Pending {
+                    target: target.clone(),
+                    priority,
+                    data,
+                };
+                if out.send(pending).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Turns a reply into the lines to send, splitting it to fit
+///
+/// Twitch's limit is for the text of the message, so the header doesn't count
+/// against it but anything added around the text does
+fn frame(
+    resp: Reply<String>,
+    target: &str,
+    sender: Option<&str>,
+    parent_id: Option<&str>,
+) -> Vec<String> {
+    let header = format!("PRIVMSG {target} :");
+    let (header, prefix, suffix, resp) = match (resp, parent_id, sender) {
+        (Reply::Reply(resp) | Reply::Problem(resp), Some(id), ..) => (
+            format!("@reply-parent-msg-id={} {header}", escape(id)),
+            String::new(),
+            "",
+            resp,
+        ),
+        (Reply::Reply(resp) | Reply::Problem(resp), None, Some(sender)) => {
+            (header, format!("{sender}: "), "", resp)
+        }
+        (Reply::Action(resp), ..) => (header, String::from("\x01ACTION "), "\x01", resp),
+        (
+            Reply::Say(resp) | Reply::Reply(resp) | Reply::Problem(resp) | Reply::Whisper(resp),
+            ..,
+        ) => (header, String::new(), "", resp),
+    };
+
+    let splitter = Splitter::TWITCH
+        .with_marker("…")
+        .reserve(prefix.len() + suffix.len());
+    resp.lines()
+        .flat_map(|line| splitter.split(line))
+        .map(|line| format!("{header}{prefix}{line}{suffix}\r\n"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the text of each line, this is what counts against the limit
+    fn text<'a>(line: &'a str, header: &str) -> &'a str {
+        line.strip_prefix(header)
+            .and_then(|line| line.strip_suffix("\r\n"))
+            .unwrap()
+    }
+
+    #[test]
+    fn threaded() {
+        let id = "b34ccfc7-4977-403a-8a94-33c6bac34fb8";
+        let lines = frame(
+            Reply::Reply("hello".into()),
+            "#museun",
+            Some("someone"),
+            Some(id),
+        );
+        assert_eq!(
+            lines,
+            [format!(
+                "@reply-parent-msg-id={id} PRIVMSG #museun :hello\r\n"
+            )]
+        );
+
+        // the id is a tag value, so it cannot end the tags early
+        let lines = frame(
+            Reply::Problem("hello".into()),
+            "#museun",
+            None,
+            Some("x;y PRIVMSG #other :pwned"),
+        );
+        assert_eq!(
+            lines,
+            ["@reply-parent-msg-id=x\\:y\\sPRIVMSG\\s#other\\s:pwned PRIVMSG #museun :hello\r\n"]
+        );
+
+        let header = format!("@reply-parent-msg-id={id} PRIVMSG #museun :");
+        let lines = frame(Reply::Reply("a ".repeat(400)), "#museun", None, Some(id));
+        assert_eq!(lines.len(), 2);
+        for line in &lines {
+            assert!(text(line, &header).len() <= 500);
+        }
+        assert!(text(&lines[0], &header).len() > 490);
+    }
+
+    #[test]
+    fn sender_fallback() {
+        let lines = frame(
+            Reply::Reply("hello".into()),
+            "#museun",
+            Some("someone"),
+            None,
+        );
+        assert_eq!(lines, ["PRIVMSG #museun :someone: hello\r\n"]);
+
+        let lines = frame(Reply::Say("hello".into()), "#museun", Some("someone"), None);
+        assert_eq!(lines, ["PRIVMSG #museun :hello\r\n"]);
+
+        let lines = frame(
+            Reply::Reply("a ".repeat(400)),
+            "#museun",
+            Some("someone"),
+            None,
+        );
+        assert_eq!(lines.len(), 2);
+        for line in &lines {
+            let text = text(line, "PRIVMSG #museun :");
+            assert!(text.starts_with("someone: "));
+            assert!(text.len() <= 500);
+        }
+    }
+
+    #[test]
+    fn action() {
+        let lines = frame(
+            Reply::Action("waves".into()),
+            "#museun",
+            Some("someone"),
+            Some("b34ccfc7-4977-403a-8a94-33c6bac34fb8"),
+        );
+        assert_eq!(lines, ["PRIVMSG #museun :\x01ACTION waves\x01\r\n"]);
+
+        let lines = frame(Reply::Action("a ".repeat(400)), "#museun", None, None);
+        assert_eq!(lines.len(), 2);
+        for line in &lines {
+            let text = text(line, "PRIVMSG #museun :");
+            assert!(text.starts_with("\x01ACTION ") && text.ends_with('\x01'));
+            assert!(text.len() <= 500);
+        }
+    }
+
+    #[test]
+    fn tags() {
+        let msg = Message {
+            tags: Arc::new(crate::irc::Tags::parse("@id=abc;user-id=1234")),
+            sender: "museun".into(),
+            target: "#museun".into(),
+            data: "hello".into(),
+            timestamp: time::OffsetDateTime::now_utc(),
+        };
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let msg = crate::Message::<Box<[u8]>>::new::<Twitch>(msg, tx);
+
+        let tags = msg.tags().unwrap();
+        assert_eq!(tags.id.as_deref(), Some("abc"));
+        assert_eq!(tags.user_id, Some(1234));
+    }
+}
@@ -1,12 +1,13 @@
 use tokio::{
     io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt},
-    net::TcpStream,
-    sync::mpsc::{Sender, UnboundedReceiver},
+    sync::mpsc::Receiver,
+    task::JoinHandle,
 };
 
-use crate::{global::GlobalItem, handler::Reply, Message, Replier, Response, Templates};
-
-use super::raw::{parse_line, Command, Line};
+use super::{
+    raw::{parse_line, Command, Line},
+    transport::{BoxedStream, Transport},
+};
 
 pub fn map_io_err<T>(err: Result<T, std::io::Error>) -> anyhow::Result<T> {
     use std::io::ErrorKind::*;
@@ -42,58 +43,30 @@ make_error! {
 #[derive(Debug)]
 pub struct Identity {
     pub display_name: String,
-    pub user_id: u64,
+    /// Anonymous logins don't have one
+    pub user_id: Option<u64>,
 }
 
-pub async fn read_responses<R>(
-    msg: Message<R>,
-    mut recv: UnboundedReceiver<Reply<Box<dyn Response>>>,
-    out: Sender<String>,
-) where
-    R: Replier,
-{
-    use crate::templates::Variant::Default as Irc;
-    while let Some(resp) = recv.recv().await {
-        let resp = resp
-            .map(|resp| Templates::get().render(&resp, Irc))
-            .transpose();
-
-        let resp = match resp {
-            Some(inner) => inner,
-            None => continue,
-        };
-
-        let Message { sender, target, .. } = &msg;
-
-        fn format_lines(left: String, right: &str) -> Vec<String> {
-            right
-                .lines()
-                .map(|right| format!("{left}{right}\r\n"))
-                .collect()
-        }
-
-        let data = match resp {
-            Reply::Say(resp) => format_lines(format!("PRIVMSG {target} :"), &resp),
-            Reply::Reply(resp) | Reply::Problem(resp) => {
-                format_lines(format!("PRIVMSG {target} :{sender}: "), &resp)
-            }
-        };
-
-        for line in data {
-            if out.send(line).await.is_err() {
-                break;
-            }
-        }
-    }
+/// Without an `oauth` token the connection is anonymous, and can only read chat
+pub async fn connect(addr: &str, name: &str, oauth: Option<&str>) -> anyhow::Result<BoxedStream> {
+    let (transport, addr) = Transport::from_address(addr)?;
+    log::debug!("connecting to {addr} using {transport:?}");
+    connect_with(&transport, addr, name, oauth).await
 }
 
-pub async fn connect(addr: &str, name: &str, oauth: &str) -> anyhow::Result<TcpStream> {
-    let mut stream = map_io_err(TcpStream::connect(addr).await)?;
+pub async fn connect_with(
+    transport: &Transport,
+    addr: &str,
+    name: &str,
+    oauth: Option<&str>,
+) -> anyhow::Result<BoxedStream> {
+    let mut stream = transport.connect(addr).await?;
+    let pass = oauth.map(|oauth| format!("PASS {oauth}\r\n"));
     for cap in [
         "CAP REQ :twitch.tv/membership\r\n",
         "CAP REQ :twitch.tv/tags\r\n",
         "CAP REQ :twitch.tv/commands\r\n",
-        &format!("PASS {oauth}\r\n"),
+        pass.as_deref().unwrap_or_default(),
         &format!("NICK {name}\r\n"),
     ] {
         map_io_err(stream.write_all(cap.as_bytes()).await)?;
@@ -111,33 +84,77 @@ where
     write_raw(&data, conn).await
 }
 
+pub async fn part<A>(channel: &str, conn: A) -> anyhow::Result<()>
+where
+    A: AsyncWrite + Unpin + Send + Sized,
+{
+    let data = format!("PART {channel}\r\n");
+    write_raw(&data, conn).await
+}
+
+/// Writes a single line, see [`sanitize`]
 pub async fn write_raw<A>(data: &str, mut conn: A) -> anyhow::Result<()>
 where
     A: AsyncWrite + Unpin + Send + Sized,
 {
+    let data = sanitize(data);
     log::trace!("-> {}", data.escape_debug());
     map_io_err(conn.write_all(data.as_bytes()).await)?;
     map_io_err(conn.flush().await)
 }
 
-pub async fn wait_for_ready<A>(buf: &mut String, mut conn: A) -> anyhow::Result<Identity>
+/// Makes sure `data` is exactly one line
+///
+/// User supplied text ends up in templates, so any CR or LF (even a lone one) is
+/// replaced with a space and any other control character, except for the `\x01`
+/// used to frame an action, is removed. This keeps it from ending the line early
+/// and starting a new command
+pub(super) fn sanitize(data: &str) -> String {
+    let line = data.strip_suffix("\r\n").unwrap_or(data);
+    let mut out = String::with_capacity(line.len() + 2);
+    for ch in line.chars() {
+        match ch {
+            '\r' | '\n' | '\t' => out.push(' '),
+            '\x01' => out.push('\x01'),
+            ch if ch.is_control() => {}
+            ch => out.push(ch),
+        }
+    }
+    out.push_str("\r\n");
+    out
+}
+
+/// Waits for the server to accept the login
+///
+/// Anonymous logins never get a `GLOBALUSERSTATE`, so they are ready once welcomed
+/// and their identity is just the `name` they connected with
+pub async fn wait_for_ready<A>(
+    buf: &mut String,
+    mut conn: A,
+    name: &str,
+    anonymous: bool,
+) -> anyhow::Result<Identity>
 where
     A: AsyncBufRead + AsyncWrite + Unpin + Send + Sized,
 {
     loop {
-        if let Command::GlobalUserState {
-            display_name,
-            user_id,
-        } = read_line(buf, &mut conn).await?.command
-        {
-            let display_name = display_name.to_string();
-            log::debug!("ready: {display_name} ({user_id})");
-
-            return Ok(Identity {
+        let identity = match read_line(buf, &mut conn).await?.command {
+            Command::GlobalUserState {
                 display_name,
                 user_id,
-            });
-        }
+            } => Identity {
+                display_name: display_name.to_string(),
+                user_id: Some(user_id),
+            },
+            Command::Ready if anonymous => Identity {
+                display_name: name.to_string(),
+                user_id: None,
+            },
+            _ => continue,
+        };
+
+        log::debug!("ready: {identity:?}");
+        return Ok(identity);
     }
 }
 
@@ -150,6 +167,14 @@ where
         0 => return Err(Eof.into()),
         n => &buf[..n],
     };
+    handle_line(buf, conn).await
+}
+
+/// Parses a line that was read, answering a PING and turning an ERROR into an error
+pub async fn handle_line<A>(buf: &str, mut conn: A) -> anyhow::Result<Line<'_>>
+where
+    A: AsyncWrite + Unpin + Send + Sized,
+{
     log::trace!("<- {}", buf.escape_debug());
 
     let line = parse_line(buf)
@@ -166,3 +191,84 @@ where
     }
     Ok(line)
 }
+
+/// Reads lines in a background task
+///
+/// Waiting on [`Lines::next`] can be cancelled without losing part of a line,
+/// unlike reading from the connection directly. The task stops when this is dropped
+pub struct Lines {
+    recv: Receiver<anyhow::Result<String>>,
+    task: JoinHandle<()>,
+}
+
+impl Lines {
+    pub fn spawn<A>(mut conn: A) -> Self
+    where
+        A: AsyncBufRead + Unpin + Send + 'static,
+    {
+        let (tx, recv) = tokio::sync::mpsc::channel(32);
+        let task = tokio::spawn(async move {
+            loop {
+                let mut buf = String::with_capacity(1024);
+                let line = match map_io_err(conn.read_line(&mut buf).await) {
+                    Ok(0) => Err(Eof.into()),
+                    Ok(..) => Ok(buf),
+                    Err(err) => Err(err),
+                };
+                let done = line.is_err();
+                if tx.send(line).await.is_err() || done {
+                    break;
+                }
+            }
+        });
+        Self { recv, task }
+    }
+
+    /// Waits for the next line, this is cancel-safe
+    pub async fn next(&mut self) -> anyhow::Result<String> {
+        self.recv.recv().await.unwrap_or_else(|| Err(Eof.into()))
+    }
+}
+
+impl Drop for Lines {
+    fn drop(&mut self) {
+        self.task.abort()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn written(data: &str) -> String {
+        let mut out = vec![];
+        write_raw(data, &mut out).await.unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[tokio::test]
+    async fn sanitize_injection() {
+        // e.g. `!add !evil hello<CR>PRIVMSG #other :pwned` and then `!evil`
+        let body = "hello\rPRIVMSG #other :pwned";
+        let out = written(&format!("PRIVMSG #test :{body}\r\n")).await;
+        assert_eq!(out, "PRIVMSG #test :hello PRIVMSG #other :pwned\r\n");
+
+        let body = "hello\r\nJOIN #other\nPART #test";
+        let out = written(&format!("PRIVMSG #test :{body}\r\n")).await;
+        assert_eq!(out, "PRIVMSG #test :hello  JOIN #other PART #test\r\n");
+        assert_eq!(out.matches("\r\n").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn sanitize_control() {
+        let out = written("PRIVMSG #test :a\0b\x07c\x1bd\u{85}e\r\n").await;
+        assert_eq!(out, "PRIVMSG #test :abcde\r\n");
+
+        let out = written("PRIVMSG #test :\x01ACTION waves\x01\r\n").await;
+        assert_eq!(out, "PRIVMSG #test :\x01ACTION waves\x01\r\n");
+
+        // missing line endings are added
+        assert_eq!(written("PING :shakey").await, "PING :shakey\r\n");
+        assert_eq!(written("日本語 🦀\r\n").await, "日本語 🦀\r\n");
+    }
+}
@@ -0,0 +1,345 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::handler::Reply;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low,
+    Normal,
+}
+
+impl<T> From<&Reply<T>> for Priority {
+    fn from(reply: &Reply<T>) -> Self {
+        match reply {
+            Reply::Problem(..) => Self::Low,
+            Reply::Say(..) | Reply::Reply(..) | Reply::Action(..) | Reply::Whisper(..) => {
+                Self::Normal
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Pending {
+    pub target: Arc<str>,
+    pub priority: Priority,
+    pub data: String,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Limits {
+    /// Messages per window in channels where we aren't a moderator
+    pub normal: u32,
+    /// Messages per window in total, and in channels where we are a moderator
+    pub elevated: u32,
+    pub window: Duration,
+    /// Minimum time between messages in a single channel where we aren't a moderator
+    pub per_channel: Duration,
+    /// How many messages can be queued before they start to get dropped
+    pub capacity: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            normal: 20,
+            elevated: 100,
+            window: Duration::from_secs(30),
+            per_channel: Duration::from_secs(1),
+            capacity: 32,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Channel {
+    moderator: bool,
+    vip: bool,
+    slow: Duration,
+    last: Option<Instant>,
+}
+
+impl Channel {
+    fn interval(&self, limits: &Limits) -> Duration {
+        match () {
+            _ if self.moderator => Duration::ZERO,
+            _ if self.vip => limits.per_channel,
+            _ => limits.per_channel.max(self.slow),
+        }
+    }
+
+    fn wait(&self, limits: &Limits, now: Instant) -> Duration {
+        self.last
+            .map(|last| (last + self.interval(limits)).saturating_duration_since(now))
+            .unwrap_or_default()
+    }
+}
+
+struct Bucket {
+    capacity: f64,
+    tokens: f64,
+    per_sec: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    fn new(capacity: u32, window: Duration, now: Instant) -> Self {
+        let capacity = capacity as f64;
+        Self {
+            capacity,
+            tokens: capacity,
+            per_sec: capacity / window.as_secs_f64(),
+            last: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_sec).min(self.capacity);
+        self.last = self.last.max(now);
+    }
+
+    fn wait(&self) -> Duration {
+        if self.tokens >= 1.0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64((1.0 - self.tokens) / self.per_sec)
+    }
+
+    fn take(&mut self) {
+        self.tokens -= 1.0;
+    }
+}
+
+/// A token-bucket limited queue of outgoing messages
+///
+/// This doesn't read the clock itself, the current time is always provided
+pub struct SendQueue {
+    limits: Limits,
+    normal: Bucket,
+    elevated: Bucket,
+    channels: HashMap<Arc<str>, Channel>,
+    pending: VecDeque<Pending>,
+}
+
+impl SendQueue {
+    pub fn new(now: Instant) -> Self {
+        Self::with_limits(Limits::default(), now)
+    }
+
+    pub fn with_limits(limits: Limits, now: Instant) -> Self {
+        Self {
+            normal: Bucket::new(limits.normal, limits.window, now),
+            elevated: Bucket::new(limits.elevated, limits.window, now),
+            limits,
+            channels: HashMap::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn set_privileges(&mut self, channel: &str, moderator: bool, vip: bool) {
+        let channel = self.channel_mut(channel);
+        channel.moderator = moderator;
+        channel.vip = vip;
+    }
+
+    pub fn set_slow_mode(&mut self, channel: &str, slow: Duration) {
+        self.channel_mut(channel).slow = slow;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Queues a message, returning the message that was dropped if the queue was full
+    pub fn push(&mut self, item: Pending) -> Option<Pending> {
+        if self.pending.len() < self.limits.capacity {
+            self.pending.push_back(item);
+            return None;
+        }
+
+        let lowest = self.pending.iter().map(|p| p.priority).min()?;
+        if lowest > item.priority {
+            return Some(item);
+        }
+
+        let pos = self.pending.iter().position(|p| p.priority == lowest)?;
+        let dropped = self.pending.remove(pos);
+        self.pending.push_back(item);
+        dropped
+    }
+
+    /// Takes the next message that can be sent right now
+    pub fn pop(&mut self, now: Instant) -> Option<Pending> {
+        self.refill(now);
+
+        let pos = self
+            .ordered()
+            .find(|&pos| self.wait_for(&self.pending[pos], now).is_zero())?;
+        let item = self.pending.remove(pos)?;
+
+        let channel = self.channel_mut(&item.target);
+        channel.last.replace(now);
+        let elevated = channel.moderator;
+
+        self.elevated.take();
+        if !elevated {
+            self.normal.take();
+        }
+
+        Some(item)
+    }
+
+    /// How long until the next message can be sent, if there are any messages
+    pub fn next_ready(&mut self, now: Instant) -> Option<Duration> {
+        self.refill(now);
+        self.pending
+            .iter()
+            .map(|item| self.wait_for(item, now))
+            .min()
+    }
+
+    fn ordered(&self) -> impl Iterator<Item = usize> + '_ {
+        let by = |priority| {
+            self.pending
+                .iter()
+                .enumerate()
+                .filter(move |(_, p)| p.priority == priority)
+                .map(|(i, _)| i)
+        };
+        by(Priority::Normal).chain(by(Priority::Low))
+    }
+
+    fn wait_for(&self, item: &Pending, now: Instant) -> Duration {
+        let (channel, elevated) = match self.channels.get(&item.target) {
+            Some(channel) => (channel.wait(&self.limits, now), channel.moderator),
+            None => (Duration::ZERO, false),
+        };
+
+        let bucket = match elevated {
+            true => self.elevated.wait(),
+            false => self.elevated.wait().max(self.normal.wait()),
+        };
+
+        channel.max(bucket)
+    }
+
+    fn refill(&mut self, now: Instant) {
+        self.normal.refill(now);
+        self.elevated.refill(now);
+    }
+
+    fn channel_mut(&mut self, channel: &str) -> &mut Channel {
+        if !self.channels.contains_key(channel) {
+            self.channels.insert(channel.into(), Channel::default());
+        }
+        self.channels
+            .get_mut(channel)
+            .expect("channel should exist")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(target: &str, priority: Priority, data: &str) -> Pending {
+        Pending {
+            target: target.into(),
+            priority,
+            data: data.into(),
+        }
+    }
+
+    fn drain(queue: &mut SendQueue, now: Instant) -> Vec<String> {
+        std::iter::from_fn(|| queue.pop(now))
+            .map(|item| item.data)
+            .collect()
+    }
+
+    #[test]
+    fn token_bucket() {
+        let start = Instant::now();
+        let mut queue = SendQueue::with_limits(
+            Limits {
+                window: Duration::from_secs(20),
+                capacity: 1000,
+                ..Limits::default()
+            },
+            start,
+        );
+        queue.set_privileges("#modded", true, false);
+
+        for i in 0..30 {
+            queue.push(pending(&format!("#c{i}"), Priority::Normal, "hello"));
+        }
+        assert_eq!(drain(&mut queue, start).len(), 20);
+        assert_eq!(queue.next_ready(start), Some(Duration::from_secs(1)));
+
+        // a moderator channel still counts against the total
+        for _ in 0..100 {
+            queue.push(pending("#modded", Priority::Normal, "hello"));
+        }
+        assert_eq!(drain(&mut queue, start).len(), 80);
+
+        let later = start + Duration::from_secs(1);
+        assert_eq!(drain(&mut queue, later).len(), 5);
+    }
+
+    #[test]
+    fn slow_mode() {
+        let start = Instant::now();
+        let mut queue = SendQueue::new(start);
+        queue.set_slow_mode("#slow", Duration::from_secs(10));
+        queue.set_slow_mode("#vip", Duration::from_secs(10));
+        queue.set_privileges("#vip", false, true);
+
+        for channel in ["#slow", "#slow", "#vip", "#vip"] {
+            queue.push(pending(channel, Priority::Normal, channel));
+        }
+
+        assert_eq!(drain(&mut queue, start), ["#slow", "#vip"]);
+        assert_eq!(queue.next_ready(start), Some(Duration::from_secs(1)));
+
+        let later = start + Duration::from_secs(1);
+        assert_eq!(drain(&mut queue, later), ["#vip"]);
+        assert_eq!(queue.next_ready(later), Some(Duration::from_secs(9)));
+
+        let later = start + Duration::from_secs(10);
+        assert_eq!(drain(&mut queue, later), ["#slow"]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn drops_problems_first() {
+        let start = Instant::now();
+        let mut queue = SendQueue::with_limits(
+            Limits {
+                capacity: 2,
+                ..Limits::default()
+            },
+            start,
+        );
+
+        assert!(queue
+            .push(pending("#c", Priority::Low, "problem"))
+            .is_none());
+        assert!(queue
+            .push(pending("#c", Priority::Normal, "say 1"))
+            .is_none());
+
+        let dropped = queue.push(pending("#c", Priority::Normal, "say 2"));
+        assert_eq!(dropped.unwrap().data, "problem");
+
+        let dropped = queue.push(pending("#c", Priority::Low, "problem"));
+        assert_eq!(dropped.unwrap().data, "problem");
+
+        assert_eq!(drain(&mut queue, start), ["say 1"]);
+        let later = start + Duration::from_secs(1);
+        assert_eq!(drain(&mut queue, later), ["say 2"]);
+    }
+}
@@ -12,9 +12,14 @@ pub enum Command<'a> {
     Ping {
         token: &'a str,
     },
+    Pong {
+        token: &'a str,
+    },
     Error {
         error: &'a str,
     },
+    /// The welcome (`001`) or the end of the MOTD (`376`)
+    Ready,
     GlobalUserState {
         display_name: &'a str,
         user_id: u64,
@@ -25,6 +30,35 @@ pub enum Command<'a> {
         target: &'a str,
         data: &'a str,
     },
+    UserState {
+        tags: Option<&'a str>,
+        channel: &'a str,
+    },
+    RoomState {
+        tags: Option<&'a str>,
+        channel: &'a str,
+    },
+    UserNotice {
+        tags: Option<&'a str>,
+        channel: &'a str,
+        data: Option<&'a str>,
+    },
+    ClearChat {
+        tags: Option<&'a str>,
+        channel: &'a str,
+        user: Option<&'a str>,
+    },
+    ClearMsg {
+        tags: Option<&'a str>,
+        channel: &'a str,
+        data: &'a str,
+    },
+    Notice {
+        tags: Option<&'a str>,
+        target: &'a str,
+        data: &'a str,
+    },
+    Reconnect,
     Ignored,
 }
 
@@ -63,6 +97,13 @@ pub fn parse_line(input: &str) -> Result<Line<'_>, &'static str> {
     fn data<'a>(input: &mut &'a str) -> Option<&'a str> {
         Some(input.trim_end()).filter(|s| !s.is_empty())
     }
+    // the channel is the data when there's no trailing parameter
+    fn channel<'a>(args: &[&'a str], data: Option<&'a str>) -> Result<&'a str, &'static str> {
+        args.first()
+            .copied()
+            .or_else(|| data.map(str::trim))
+            .ok_or("missing channel")
+    }
 
     let line = input;
     let raw = &mut input.trim();
@@ -77,6 +118,9 @@ pub fn parse_line(input: &str) -> Result<Line<'_>, &'static str> {
         "PING" => Command::Ping {
             token: data.ok_or("missing token")?,
         },
+        "PONG" => Command::Pong {
+            token: data.ok_or("missing token")?,
+        },
         "ERROR" => Command::Error {
             error: data.ok_or("missing message")?,
         },
@@ -86,6 +130,7 @@ pub fn parse_line(input: &str) -> Result<Line<'_>, &'static str> {
             target: args.first().ok_or("missing target")?,
             data: data.ok_or("missing data")?,
         },
+        "001" | "376" => Command::Ready,
         "GLOBALUSERSTATE" => {
             let tags = tags
                 .map(as_tag_map)
@@ -98,6 +143,49 @@ pub fn parse_line(input: &str) -> Result<Line<'_>, &'static str> {
                     .ok_or("missing user-id tag")?,
             }
         }
+        "USERSTATE" => Command::UserState {
+            tags,
+            channel: channel(&args, data)?,
+        },
+        "ROOMSTATE" => Command::RoomState {
+            tags,
+            channel: channel(&args, data)?,
+        },
+        "USERNOTICE" => match args.first() {
+            Some(channel) => Command::UserNotice {
+                tags,
+                channel,
+                data,
+            },
+            None => Command::UserNotice {
+                tags,
+                channel: channel(&args, data)?,
+                data: None,
+            },
+        },
+        "CLEARCHAT" => match args.first() {
+            Some(channel) => Command::ClearChat {
+                tags,
+                channel,
+                user: data,
+            },
+            None => Command::ClearChat {
+                tags,
+                channel: channel(&args, data)?,
+                user: None,
+            },
+        },
+        "CLEARMSG" => Command::ClearMsg {
+            tags,
+            channel: args.first().ok_or("missing channel")?,
+            data: data.ok_or("missing data")?,
+        },
+        "NOTICE" => Command::Notice {
+            tags,
+            target: args.first().ok_or("missing target")?,
+            data: data.ok_or("missing data")?,
+        },
+        "RECONNECT" => Command::Reconnect,
         _ => Command::Ignored,
     };
 
@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+use tokio::io::AsyncWriteExt;
+
+use super::{proto::sanitize, Pending};
+
+/// Where replies go when connected in read-only mode
+#[derive(Clone, Debug)]
+pub enum Sink {
+    Log,
+    /// Lines are appended to this file
+    File(PathBuf),
+}
+
+impl Sink {
+    /// Writes a line the same way it would be sent, see [`sanitize`]
+    pub async fn write(&self, pending: &Pending) -> anyhow::Result<()> {
+        let line = sanitize(&pending.data);
+        let line = line.trim_end();
+        match self {
+            Self::Log => log::info!("[{}] (read-only) {line}", pending.target),
+            Self::File(path) => {
+                let mut file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?;
+                file.write_all(format!("{line}\n").as_bytes()).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::irc::Priority;
+
+    #[tokio::test]
+    async fn sanitized() {
+        let path =
+            std::env::temp_dir().join(format!("shakey-sink-test-{}.log", std::process::id()));
+        let _ = tokio::fs::remove_file(&path).await;
+
+        let sink = Sink::File(path.clone());
+        for data in [
+            "PRIVMSG #test :hello\r[#test] forged entry\r\n",
+            "PRIVMSG #test :a\x1b[2Jb\n\0c",
+        ] {
+            let pending = Pending {
+                target: "#test".into(),
+                priority: Priority::Normal,
+                data: data.into(),
+            };
+            sink.write(&pending).await.unwrap();
+        }
+
+        let data = tokio::fs::read_to_string(&path).await.unwrap();
+        assert_eq!(
+            data,
+            "PRIVMSG #test :hello [#test] forged entry\nPRIVMSG #test :a[2Jb c\n"
+        );
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+}
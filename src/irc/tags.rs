@@ -0,0 +1,301 @@
+use std::{collections::HashMap, ops::Range, sync::Arc};
+
+#[derive(Clone, Debug, Default)]
+pub struct Tags {
+    pub id: Option<Arc<str>>,
+    pub user_id: Option<u64>,
+    pub display_name: Option<Arc<str>>,
+    pub color: Option<Color>,
+    pub emotes: Vec<Emote>,
+    pub bits: Option<u64>,
+    pub first_msg: bool,
+    pub reply_parent: Option<ReplyParent>,
+    pub badges: Vec<Badge>,
+    pub badge_info: Vec<Badge>,
+    map: HashMap<Box<str>, Box<str>>,
+}
+
+impl Tags {
+    pub fn parse(input: &str) -> Self {
+        let map = input
+            .strip_prefix('@')
+            .unwrap_or(input)
+            .split(';')
+            .filter_map(|s| s.split_once('='))
+            .map(|(k, v)| (Box::from(k), unescape(v).into_boxed_str()))
+            .collect::<HashMap<_, _>>();
+
+        let get = |key: &str| map.get(key).map(|s| &**s).filter(|s| !s.is_empty());
+        let get_arc = |key: &str| get(key).map(Arc::from);
+        let get_parsed = |key: &str| get(key).and_then(|s| s.parse().ok());
+
+        let reply_parent = get("reply-parent-msg-id").map(|msg_id| ReplyParent {
+            msg_id: msg_id.into(),
+            user_id: get_parsed("reply-parent-user-id"),
+            user_login: get_arc("reply-parent-user-login"),
+            display_name: get_arc("reply-parent-display-name"),
+            body: get_arc("reply-parent-msg-body"),
+        });
+
+        Self {
+            id: get_arc("id"),
+            user_id: get_parsed("user-id"),
+            display_name: get_arc("display-name"),
+            color: get("color").and_then(Color::parse),
+            emotes: get("emotes").map(Emote::parse_list).unwrap_or_default(),
+            bits: get_parsed("bits"),
+            first_msg: get("first-msg") == Some("1"),
+            reply_parent,
+            badges: get("badges").map(Badge::parse_list).unwrap_or_default(),
+            badge_info: get("badge-info").map(Badge::parse_list).unwrap_or_default(),
+            map,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.map.get(key).map(|s| &**s)
+    }
+
+    pub fn get_parsed<T>(&self, key: &str) -> Option<T>
+    where
+        T: std::str::FromStr,
+    {
+        self.get(key)?.parse().ok()
+    }
+
+    pub fn badge(&self, name: &str) -> Option<&Badge> {
+        self.badges.iter().find(|badge| &*badge.name == name)
+    }
+
+    pub fn has_badge(&self, name: &str) -> bool {
+        self.badge(name).is_some()
+    }
+
+    pub fn subscriber_months(&self) -> Option<u32> {
+        self.badge_info
+            .iter()
+            .chain(&self.badges)
+            .find(|badge| matches!(&*badge.name, "subscriber" | "founder"))
+            .and_then(|badge| badge.version.parse().ok())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Color(pub u8, pub u8, pub u8);
+
+impl Color {
+    fn parse(input: &str) -> Option<Self> {
+        let input = input.strip_prefix('#')?;
+        if input.len() != 6 || !input.is_ascii() {
+            return None;
+        }
+        let part = |i: usize| u8::from_str_radix(&input[i..i + 2], 16).ok();
+        Some(Self(part(0)?, part(2)?, part(4)?))
+    }
+}
+
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self(r, g, b) = self;
+        write!(f, "#{r:02X}{g:02X}{b:02X}")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Badge {
+    pub name: Box<str>,
+    pub version: Box<str>,
+}
+
+impl Badge {
+    fn parse_list(input: &str) -> Vec<Self> {
+        input
+            .split(',')
+            .filter_map(|badge| badge.split_once('/'))
+            .map(|(name, version)| Self {
+                name: name.into(),
+                version: version.into(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Emote {
+    pub id: Box<str>,
+    /// Half-open ranges of *character* (not byte) offsets into the message
+    pub ranges: Vec<Range<usize>>,
+}
+
+impl Emote {
+    fn parse_list(input: &str) -> Vec<Self> {
+        // emotes=25:0-4,12-16/1902:6-10
+        input
+            .split('/')
+            .filter_map(|emote| emote.split_once(':'))
+            .map(|(id, ranges)| Self {
+                id: id.into(),
+                ranges: ranges
+                    .split(',')
+                    .filter_map(|range| range.split_once('-'))
+                    .filter_map(|(start, end)| {
+                        Some(start.parse().ok()?..end.parse::<usize>().ok()? + 1)
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplyParent {
+    pub msg_id: Arc<str>,
+    pub user_id: Option<u64>,
+    pub user_login: Option<Arc<str>>,
+    pub display_name: Option<Arc<str>>,
+    pub body: Option<Arc<str>>,
+}
+
+// https://ircv3.net/specs/extensions/message-tags.html#escaping-values
+fn unescape(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut iter = input.chars();
+    while let Some(ch) = iter.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+
+        match iter.next() {
+            Some(':') => out.push(';'),
+            Some('s') => out.push(' '),
+            Some('r') => out.push('\r'),
+            Some('n') => out.push('\n'),
+            Some(ch) => out.push(ch),
+            None => {}
+        }
+    }
+    out
+}
+
+/// The inverse of [`unescape`], for sending a tag back to the server
+pub(super) fn escape(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for ch in input.chars() {
+        match ch {
+            ';' => out.push_str(r"\:"),
+            ' ' => out.push_str(r"\s"),
+            '\\' => out.push_str(r"\\"),
+            '\r' => out.push_str(r"\r"),
+            '\n' => out.push_str(r"\n"),
+            ch => out.push(ch),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescape_values() {
+        assert_eq!(unescape(r"hello\sworld"), "hello world");
+        assert_eq!(unescape(r"a\:b"), "a;b");
+        assert_eq!(unescape(r"back\\slash"), r"back\slash");
+        assert_eq!(unescape(r"line\rbreak\n"), "line\rbreak\n");
+        assert_eq!(unescape(r"\q"), "q");
+        assert_eq!(unescape(r"trailing\"), "trailing");
+    }
+
+    #[test]
+    fn escape_values() {
+        assert_eq!(escape("hello world"), r"hello\sworld");
+        assert_eq!(escape("a;b"), r"a\:b");
+        assert_eq!(escape(r"back\slash"), r"back\\slash");
+        assert_eq!(escape("line\rbreak\n"), r"line\rbreak\n");
+
+        let input = "x; PRIVMSG #other :pwned\r\n\\";
+        assert_eq!(unescape(&escape(input)), input);
+    }
+
+    #[test]
+    fn privmsg() {
+        let tags = Tags::parse(
+            "@badge-info=subscriber/22;badges=broadcaster/1,subscriber/3012,sub-gifter/50;\
+            client-nonce=1c3e9e8a8a3f4d0b;color=#FF69B4;display-name=museun;emotes=25:0-4,12-16/1902:6-10;\
+            first-msg=0;flags=;id=b34ccfc7-4977-403a-8a94-33c6bac34fb8;mod=0;room-id=23196011;\
+            subscriber=1;tmi-sent-ts=1662000000000;turbo=0;user-id=23196011;user-type=",
+        );
+
+        assert_eq!(
+            tags.id.as_deref(),
+            Some("b34ccfc7-4977-403a-8a94-33c6bac34fb8")
+        );
+        assert_eq!(tags.user_id, Some(23196011));
+        assert_eq!(tags.display_name.as_deref(), Some("museun"));
+        assert_eq!(tags.color, Some(Color(0xFF, 0x69, 0xB4)));
+        assert!(!tags.first_msg);
+        assert_eq!(tags.get("flags"), Some(""));
+        assert_eq!(tags.get("user-type"), Some(""));
+
+        let badge = |name: &str, version: &str| Badge {
+            name: name.into(),
+            version: version.into(),
+        };
+        assert_eq!(
+            tags.badges,
+            [
+                badge("broadcaster", "1"),
+                badge("subscriber", "3012"),
+                badge("sub-gifter", "50")
+            ]
+        );
+        assert_eq!(tags.badge_info, [badge("subscriber", "22")]);
+        assert!(tags.has_badge("broadcaster"));
+        assert_eq!(tags.subscriber_months(), Some(22));
+
+        assert_eq!(
+            tags.emotes,
+            [
+                Emote {
+                    id: "25".into(),
+                    ranges: vec![0..5, 12..17],
+                },
+                Emote {
+                    id: "1902".into(),
+                    ranges: std::iter::once(6..11).collect(),
+                },
+            ]
+        );
+        assert!(tags.reply_parent.is_none());
+    }
+
+    #[test]
+    fn reply_parent() {
+        let tags = Tags::parse(
+            r"@badge-info=;badges=;color=;display-name=someone;emotes=;first-msg=1;flags=;id=885196de-cb67-427a-baa8-82f9b0fcd05f;mod=0;reply-parent-display-name=museun;reply-parent-msg-body=hello\sthere\:\sfriend;reply-parent-msg-id=b34ccfc7-4977-403a-8a94-33c6bac34fb8;reply-parent-user-id=23196011;reply-parent-user-login=museun;room-id=23196011;subscriber=0;tmi-sent-ts=1662000000000;turbo=0;user-id=42;user-type=",
+        );
+
+        assert!(tags.first_msg);
+        assert!(tags.badges.is_empty());
+        assert!(tags.color.is_none());
+        assert_eq!(
+            tags.reply_parent,
+            Some(ReplyParent {
+                msg_id: "b34ccfc7-4977-403a-8a94-33c6bac34fb8".into(),
+                user_id: Some(23196011),
+                user_login: Some("museun".into()),
+                display_name: Some("museun".into()),
+                body: Some("hello there; friend".into()),
+            })
+        );
+    }
+
+    #[test]
+    fn spaces_are_kept() {
+        let tags = Tags::parse(r"@system-msg=\sspaced\sout\s;login=museun");
+        assert_eq!(tags.get("system-msg"), Some(" spaced out "));
+        assert_eq!(tags.get("login"), Some("museun"));
+    }
+}
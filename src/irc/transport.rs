@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+};
+use tokio_rustls::{
+    rustls::{ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName},
+    TlsConnector,
+};
+
+use super::proto::map_io_err;
+
+pub trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T> Stream for T where T: AsyncRead + AsyncWrite + Unpin + Send {}
+
+pub type BoxedStream = Box<dyn Stream>;
+
+#[derive(Clone)]
+pub enum Transport {
+    Plain,
+    Tls(Arc<ClientConfig>),
+}
+
+impl std::fmt::Debug for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Plain => f.write_str("Plain"),
+            Self::Tls(..) => f.write_str("Tls"),
+        }
+    }
+}
+
+impl Transport {
+    /// Splits the scheme from an address, e.g. `tls://irc.chat.twitch.tv:6697`
+    ///
+    /// Addresses without a scheme use a plain connection
+    pub fn from_address(addr: &str) -> anyhow::Result<(Self, &str)> {
+        match addr.split_once("://") {
+            Some(("tls" | "ircs", addr)) => Ok((Self::tls(), addr)),
+            Some(("tcp" | "irc", addr)) => Ok((Self::Plain, addr)),
+            None => Ok((Self::Plain, addr)),
+            Some((scheme, ..)) => anyhow::bail!("unknown scheme for '{addr}': {scheme}"),
+        }
+    }
+
+    /// Uses the Mozilla root certificates
+    pub fn tls() -> Self {
+        let mut roots = RootCertStore::empty();
+        roots.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|ta| {
+            OwnedTrustAnchor::from_subject_spki_name_constraints(
+                ta.subject,
+                ta.spki,
+                ta.name_constraints,
+            )
+        }));
+        Self::with_roots(roots)
+    }
+
+    pub fn with_roots(roots: RootCertStore) -> Self {
+        let config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Self::Tls(Arc::new(config))
+    }
+
+    pub async fn connect(&self, addr: &str) -> anyhow::Result<BoxedStream> {
+        let stream = map_io_err(TcpStream::connect(addr).await)?;
+        let config = match self {
+            Self::Plain => return Ok(Box::new(stream)),
+            Self::Tls(config) => Arc::clone(config),
+        };
+
+        let host = addr.rsplit_once(':').map_or(addr, |(host, _)| host);
+        let domain = ServerName::try_from(host)?;
+        let stream = TlsConnector::from(config).connect(domain, stream).await;
+        Ok(Box::new(map_io_err(stream)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+    };
+    use tokio_rustls::{
+        rustls::{self, ServerConfig},
+        TlsAcceptor,
+    };
+
+    use super::{super::proto::connect_with, *};
+
+    #[tokio::test]
+    async fn connect_tls() {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+        let cert_der = rustls::Certificate(cert.serialize_der().unwrap());
+        let key_der = rustls::PrivateKey(cert.serialize_private_key_der());
+
+        let config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(vec![cert_der.clone()], key_der)
+            .unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+
+        let listener = TcpListener::bind("localhost:0").await.unwrap();
+        let addr = format!("localhost:{}", listener.local_addr().unwrap().port());
+
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let stream = acceptor.accept(stream).await.unwrap();
+            let mut lines = BufReader::new(stream).lines();
+            let mut out = vec![];
+            while let Some(line) = lines.next_line().await.unwrap() {
+                out.push(line);
+            }
+            out
+        });
+
+        let mut roots = RootCertStore::empty();
+        roots.add(&cert_der).unwrap();
+
+        let mut stream = connect_with(
+            &Transport::with_roots(roots),
+            &addr,
+            "shakey",
+            Some("oauth:hunter2"),
+        )
+        .await
+        .unwrap();
+        stream.shutdown().await.unwrap();
+
+        let lines = server.await.unwrap();
+        assert!(lines.iter().any(|line| line == "PASS oauth:hunter2"));
+        assert!(lines.iter().any(|line| line == "NICK shakey"));
+    }
+
+    #[test]
+    fn from_address() {
+        let (transport, addr) = Transport::from_address("irc.chat.twitch.tv:6667").unwrap();
+        assert!(matches!(transport, Transport::Plain));
+        assert_eq!(addr, "irc.chat.twitch.tv:6667");
+
+        let (transport, addr) = Transport::from_address("tls://irc.chat.twitch.tv:6697").unwrap();
+        assert!(matches!(transport, Transport::Tls { .. }));
+        assert_eq!(addr, "irc.chat.twitch.tv:6697");
+
+        assert!(Transport::from_address("ws://irc-ws.chat.twitch.tv:80").is_err());
+    }
+}
@@ -14,7 +14,10 @@ pub mod global;
 pub mod helix;
 pub mod irc;
 pub mod modules;
+pub mod split;
+pub mod supervisor;
 
+mod bounded;
 mod get_fields;
 mod serde;
 
@@ -26,6 +29,8 @@ mod spotify;
 
 pub mod config;
 
+pub mod console;
+
 crate::make_response! {
     module: "system"
 
@@ -38,10 +43,15 @@ crate::make_response! {
     } is "invalid_usage"
 
     struct RequiresPermission {
+        permission: String,
     } is "requires_permission"
 
     struct RequiresAdmin {
     } is "requires_admin"
+
+    struct OnCooldown {
+        seconds: u64,
+    } is "on_cooldown"
 }
 
 pub fn bind_system_errors() -> anyhow::Result<()> {
@@ -54,6 +64,12 @@ include!(concat!(env!("OUT_DIR"), "/", "version.rs"));
 pub const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
 
 mod message;
-pub use message::Message;
+pub use message::{set_message_capacity, Message, SenderPriv};
+
+pub mod platform;
+pub use platform::Platform;
+
+mod event;
+pub use event::{Event, EventKind};
 
 pub mod twilight;
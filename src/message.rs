@@ -1,6 +1,6 @@
 #![cfg_attr(debug_assertions, allow(dead_code, unused_variables,))]
 
-use std::{collections::HashMap, sync::Arc};
+use std::{any::Any, sync::Arc};
 
 use once_cell::sync::OnceCell;
 use parking_lot::{Mutex, MutexGuard};
@@ -10,109 +10,78 @@ use tokio::sync::mpsc::UnboundedSender;
 use uuid::Uuid;
 
 use crate::{
-    ext::ArcExt,
+    bounded::Bounded,
+    handler::Permission,
+    irc::Tags,
+    platform::Platform,
     responses::{RequiresAdmin, RequiresPermission},
     Replier, Reply, Response,
 };
 
-pub fn add_message(msg: MessageKind) -> Uuid {
-    get_message_static()
-        .insert(msg)
-        .unwrap_or_else(|_| panic!("uuid should be unique"))
-}
-
+/// The platform's own message, along with the name of the platform
 #[derive(Clone)]
-pub enum MessageKind {
-    Twitch(crate::irc::Message),
-    Discord(crate::twilight::Message),
+struct Stored {
+    platform: &'static str,
+    item: Arc<dyn Any + Send + Sync>,
 }
 
-pub trait NarrowMessageKind: Sized {
-    fn is_twitch(&self) -> bool;
-    fn is_discord(&self) -> bool;
+fn add_message<P: Platform>(msg: P::Message) -> Uuid {
+    let stored = Stored {
+        platform: P::NAME,
+        item: Arc::new(msg),
+    };
 
-    fn into_twitch(self) -> Option<crate::irc::Message>;
-    fn into_discord(self) -> Option<crate::twilight::Message>;
+    let id = Uuid::new_v4();
+    let mut messages = get_message_static();
+    assert!(!messages.contains_key(&id), "uuid should be unique");
+    messages.insert(id, stored);
+    id
 }
 
-impl NarrowMessageKind for Arc<MessageKind> {
-    fn into_twitch(self) -> Option<crate::irc::Message> {
-        match self.unwrap_or_clone() {
-            MessageKind::Twitch(twitch) => Some(twitch),
-            _ => None,
-        }
-    }
+/// The most recent messages, so they can be looked up by id
+static MESSAGES: OnceCell<Mutex<Bounded<Uuid, Stored>>> = OnceCell::new();
 
-    fn into_discord(self) -> Option<crate::twilight::Message> {
-        match self.unwrap_or_clone() {
-            MessageKind::Discord(discord) => Some(discord),
-            _ => None,
-        }
-    }
+/// How many messages are remembered, unless [`set_message_capacity`] is used
+const DEFAULT_CAPACITY: usize = 10_000;
 
-    fn is_twitch(&self) -> bool {
-        matches!(&**self, MessageKind::Twitch { .. })
-    }
-
-    fn is_discord(&self) -> bool {
-        matches!(&**self, MessageKind::Discord { .. })
-    }
-}
-
-static MESSAGES: OnceCell<Mutex<Messages>> = OnceCell::new();
-
-fn get_message_static() -> MutexGuard<'static, Messages> {
-    MESSAGES.get_or_init(|| Mutex::new(Messages::new())).lock()
+fn get_message_static() -> MutexGuard<'static, Bounded<Uuid, Stored>> {
+    MESSAGES
+        .get_or_init(|| Mutex::new(Bounded::new(DEFAULT_CAPACITY)))
+        .lock()
 }
 
-fn lookup_message(id: Uuid) -> Option<Arc<MessageKind>> {
-    get_message_static().get(id)
+fn lookup_message(id: Uuid) -> Option<Stored> {
+    get_message_static().get(&id).cloned()
 }
 
-struct Messages {
-    map: HashMap<Uuid, Arc<MessageKind>>,
+/// Sets how many messages are remembered, the least recently used ones are forgotten first
+pub fn set_message_capacity(capacity: usize) {
+    get_message_static().set_capacity(capacity)
 }
 
-impl Messages {
-    fn new() -> Self {
-        Self {
-            map: HashMap::new(),
-        }
-    }
-
-    fn get(&self, id: Uuid) -> Option<Arc<MessageKind>> {
-        self.map.get(&id).cloned()
-    }
-
-    fn insert(&mut self, item: MessageKind) -> Result<Uuid, MessageKind> {
-        use std::collections::hash_map::Entry::*;
-        let id = Uuid::new_v4();
-        match self.map.entry(id) {
-            Occupied(..) => Err(item),
-            Vacant(e) => {
-                e.insert(Arc::new(item));
-                Ok(id)
-            }
-        }
-    }
-}
-
-#[derive(Copy, Clone, Default)]
-enum SenderPriv {
+/// What the sender of a message is allowed to do
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
+pub enum SenderPriv {
     Admin,
     Moderator,
+    Vip,
+    Subscriber,
     #[default]
     None,
 }
 
 pub struct Message<R: Replier> {
     pub(crate) id: Uuid,
+    pub(crate) platform: &'static str,
     pub(crate) timestamp: OffsetDateTime,
     pub(crate) sender: Arc<str>,
     pub(crate) target: Arc<str>,
     pub(crate) data: Arc<str>,
+    pub(crate) tags: Option<Arc<Tags>>,
 
     priv_: SenderPriv,
+    whispers: bool,
+    expects_reply: bool,
     pub(crate) reply: UnboundedSender<Reply<R>>,
 }
 
@@ -131,52 +100,35 @@ impl<R: Replier + 'static> Clone for Message<R> {
     fn clone(&self) -> Self {
         Self {
             id: self.id,
+            platform: self.platform,
             timestamp: self.timestamp,
             sender: self.sender.clone(),
             target: self.target.clone(),
             data: self.data.clone(),
+            tags: self.tags.clone(),
 
             priv_: self.priv_,
+            whispers: self.whispers,
+            expects_reply: self.expects_reply,
             reply: self.reply.clone(),
         }
     }
 }
 
 impl<R: Replier> Message<R> {
-    fn store_message(kind: MessageKind) -> Uuid {
-        add_message(kind)
-    }
-
-    pub(crate) fn twitch(msg: crate::irc::Message, reply: UnboundedSender<Reply<R>>) -> Self {
-        let is_broadcaster =
-            |k: &str, v: &str| (k == "broadcaster" && v == "1").then_some(SenderPriv::Admin);
-        let is_moderator =
-            |k: &str, v: &str| (k == "moderator" && v == "1").then_some(SenderPriv::Moderator);
-
-        let priv_ = msg
-            .badges_iter()
-            .find_map(|(k, v)| is_broadcaster(k, v).or_else(|| is_moderator(k, v)))
-            .unwrap_or_default();
-
-        Self {
-            id: Self::store_message(MessageKind::Twitch(msg.clone())),
-            timestamp: msg.timestamp,
-            sender: msg.sender,
-            target: msg.target,
-            data: msg.data,
-            priv_,
-            reply,
-        }
-    }
-
-    pub(crate) fn discord(msg: crate::twilight::Message, reply: UnboundedSender<Reply<R>>) -> Self {
+    pub(crate) fn new<P: Platform>(msg: P::Message, reply: UnboundedSender<Reply<R>>) -> Self {
+        let parts = P::parts(&msg);
         Self {
-            id: Self::store_message(MessageKind::Discord(msg.clone())),
-            timestamp: msg.timestamp,
-            sender: msg.inner.author.name.clone().into(),
-            target: msg.source,
-            data: msg.inner.content.clone().into(),
-            priv_: SenderPriv::default(),
+            priv_: P::sender_priv(&msg),
+            whispers: P::WHISPERS,
+            id: add_message::<P>(msg),
+            platform: P::NAME,
+            timestamp: parts.timestamp,
+            sender: parts.sender,
+            target: parts.target,
+            data: parts.data,
+            tags: parts.tags,
+            expects_reply: parts.expects_reply,
             reply,
         }
     }
@@ -197,6 +149,17 @@ impl<R: Replier> Message<R> {
         let item = R::problem(item);
         let _ = self.reply.send(item);
     }
+
+    pub fn action(&self, item: impl Serialize + Response + 'static) {
+        let item = R::action(item);
+        let _ = self.reply.send(item);
+    }
+
+    /// Replies privately, this is dropped where that isn't possible, see [`Message::can_whisper`]
+    pub fn whisper(&self, item: impl Serialize + Response + 'static) {
+        let item = R::whisper(item);
+        let _ = self.reply.send(item);
+    }
 }
 
 impl<R: Replier> Message<R> {
@@ -204,6 +167,21 @@ impl<R: Replier> Message<R> {
         self.id
     }
 
+    /// The name of the platform this came from, see [`Platform::NAME`]
+    pub const fn platform(&self) -> &'static str {
+        self.platform
+    }
+
+    /// Whether [`Message::whisper`] will be delivered, see [`Platform::WHISPERS`]
+    pub const fn can_whisper(&self) -> bool {
+        self.whispers
+    }
+
+    /// Whether the platform shows an error if nothing is said, see [`crate::platform::Parts`]
+    pub const fn expects_reply(&self) -> bool {
+        self.expects_reply
+    }
+
     pub const fn timestamp(&self) -> OffsetDateTime {
         self.timestamp
     }
@@ -219,6 +197,11 @@ impl<R: Replier> Message<R> {
     pub fn data(&self) -> &str {
         &self.data
     }
+
+    /// The Twitch tags, these stay with the message after [`Message::source`] has forgotten it
+    pub fn tags(&self) -> Option<&Tags> {
+        self.tags.as_deref()
+    }
 }
 
 impl<R: Replier> Message<R> {
@@ -230,10 +213,12 @@ impl<R: Replier> Message<R> {
         matches!(self.priv_, SenderPriv::Moderator)
     }
 
+    /// Whether the sender is the broadcaster or a moderator
     pub const fn is_from_elevated(&self) -> bool {
-        !(self.is_from_broadcaster() || self.is_from_moderator())
+        self.is_from_broadcaster() || self.is_from_moderator()
     }
 
+    #[deprecated(note = "use `permission: broadcaster` for the command in commands.yaml")]
     pub fn require_broadcaster(&self) -> bool {
         if !self.is_from_broadcaster() {
             self.problem(RequiresAdmin {});
@@ -242,31 +227,27 @@ impl<R: Replier> Message<R> {
         true
     }
 
+    #[deprecated(note = "use `permission: moderator` for the command in commands.yaml")]
     pub fn requires_permission(&self) -> bool {
         if !self.is_from_elevated() {
-            self.problem(RequiresPermission {});
+            self.problem(RequiresPermission {
+                permission: Permission::Moderator.as_str().to_string(),
+            });
             return false;
         }
         true
     }
 
-    pub fn is_discord(&self) -> bool {
-        lookup_message(self.id())
-            .filter(NarrowMessageKind::is_discord)
-            .is_some()
-    }
-
-    pub fn is_twitch(&self) -> bool {
-        lookup_message(self.id())
-            .filter(NarrowMessageKind::is_twitch)
-            .is_some()
-    }
-
-    pub fn as_discord(&self) -> Option<crate::twilight::Message> {
-        lookup_message(self.id()).and_then(NarrowMessageKind::into_discord)
+    /// The highest [`Permission`] the sender has
+    pub fn permission(&self) -> Permission {
+        self.priv_.into()
     }
 
-    pub fn as_twitch(&self) -> Option<crate::irc::Message> {
-        lookup_message(self.id()).and_then(NarrowMessageKind::into_twitch)
+    /// The platform's own message, if this came from `P`
+    ///
+    /// Only recent messages are remembered, this is `None` for older ones
+    pub fn source<P: Platform>(&self) -> Option<P::Message> {
+        let stored = lookup_message(self.id()).filter(|stored| stored.platform == P::NAME)?;
+        stored.item.downcast_ref::<P::Message>().cloned()
     }
 }
@@ -42,14 +42,26 @@ impl<R: Replier> Bindable<R> for Help {
 }
 
 impl Help {
+    const PER_LINE: usize = 10;
+
     fn help(&mut self, msg: &Message<impl Replier>, args: Arguments) {
         let commands = Commands::get();
         let cmd = match args.get("command") {
             Some(cmd) => cmd,
             None => {
-                let list = commands.command_names().map(ToString::to_string);
-                let commands = LimitedVec::new(10, list);
-                msg.say(responses::ListCommands { commands });
+                let list = commands
+                    .command_names()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>();
+
+                // long lists are sent privately where possible, so they don't flood the chat.
+                // otherwise only the first few are said
+                let long = list.len() > Self::PER_LINE;
+                let commands = LimitedVec::new(Self::PER_LINE, list);
+                match long && msg.can_whisper() {
+                    true => msg.whisper(responses::ListCommands { commands }),
+                    false => msg.say(responses::ListCommands { commands }),
+                }
                 return;
             }
         };
@@ -70,7 +82,7 @@ impl Help {
             }
 
             let list = cmd.aliases.iter().map(ToString::to_string);
-            let aliases = LimitedVec::new(10, list);
+            let aliases = LimitedVec::new(Self::PER_LINE, list);
 
             msg.say(responses::SpecificCommand {
                 command,
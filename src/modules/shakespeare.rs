@@ -6,7 +6,7 @@ use tokio::{sync::Mutex, time::Instant};
 
 use crate::{
     data::{Interest, InterestPath, Watch, WatchFile},
-    handler::{Bindable, Components},
+    handler::{Bindable, Components, Permission},
     Arguments, Bind, Message, Outcome, Replier,
 };
 
@@ -71,7 +71,7 @@ impl<R: Replier> Bindable<R> for Shakespeare {
             last: <Arc<Mutex<Option<_>>>>::default(),
             config,
         })?
-        .bind(Self::toggle)?
+        .bind_with(Permission::Broadcaster, Self::toggle)?
         .bind(Self::speak)?
         .listen(Self::listen)
     }
@@ -83,10 +83,6 @@ impl Shakespeare {
         let config = self.config.clone();
 
         tokio::spawn(async move {
-            if !msg.require_broadcaster() {
-                return Ok(());
-            }
-
             {
                 let mut config = config.get_mut().await;
                 let new = !config.enabled;
@@ -1,7 +1,8 @@
 use crate::{
     ext::FormatTime,
-    handler::{Bindable, Components},
+    handler::{Bindable, Components, Permission},
     helix::{data::Stream, HelixClient},
+    irc::ChannelControl,
     Arguments, Bind, Message, Outcome, Replier,
 };
 use time::OffsetDateTime;
@@ -22,10 +23,27 @@ crate::make_response! {
     struct NotStreaming {
         channel: String,
     } is "not_streaming"
+
+    struct Joined {
+        channel: String,
+    } is "joined"
+
+    struct AlreadyJoined {
+        channel: String,
+    } is "already_joined"
+
+    struct Parted {
+        channel: String,
+    } is "parted"
+
+    struct InvalidChannel {
+        channel: String,
+    } is "invalid_channel"
 }
 
 pub struct Twitch {
     client: HelixClient,
+    channels: ChannelControl,
 }
 
 #[async_trait::async_trait]
@@ -35,8 +53,13 @@ impl<R: Replier> Bindable<R> for Twitch {
     async fn bind(components: &Components) -> anyhow::Result<Bind<Self, R>> {
         let this = Self {
             client: components.get(),
+            channels: components.get(),
         };
-        Bind::create(this)?.bind(Self::uptime)?.bind(Self::viewers)
+        Bind::create(this)?
+            .bind(Self::uptime)?
+            .bind(Self::viewers)?
+            .bind_with(Permission::Broadcaster, Self::join)?
+            .bind_with(Permission::Broadcaster, Self::part)
     }
 }
 
@@ -92,6 +115,43 @@ impl Twitch {
         tokio::spawn(viewers(client, msg, args))
     }
 
+    fn join(&mut self, msg: &Message<impl Replier>, mut args: Arguments) -> impl Outcome {
+        let msg = msg.clone();
+        let channels = self.channels.clone();
+
+        tokio::spawn(async move {
+            let channel = ChannelControl::normalize(&args.take("channel"));
+            if !ChannelControl::is_valid(&channel) {
+                msg.problem(responses::InvalidChannel { channel });
+                return Ok(());
+            }
+
+            if channels.join(&channel).await? {
+                msg.say(responses::Joined { channel });
+            } else {
+                msg.problem(responses::AlreadyJoined { channel });
+            }
+            Ok(())
+        })
+    }
+
+    fn part(&mut self, msg: &Message<impl Replier>, mut args: Arguments) -> impl Outcome {
+        let msg = msg.clone();
+        let channels = self.channels.clone();
+
+        tokio::spawn(async move {
+            let channel = ChannelControl::normalize(&args.take("channel"));
+            if !ChannelControl::is_valid(&channel) {
+                msg.problem(responses::InvalidChannel { channel });
+                return Ok(());
+            }
+
+            channels.part(&channel).await?;
+            msg.say(responses::Parted { channel });
+            Ok(())
+        })
+    }
+
     async fn get_stream(
         client: &HelixClient,
         msg: &Message<impl Replier>,
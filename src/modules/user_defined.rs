@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use crate::{
     data::{Interest, InterestPath},
     ext::IterExt,
-    handler::{Bindable, Components},
+    handler::{Bindable, Components, Permission},
     Arguments, Bind, Message, Outcome, Replier,
 };
 
@@ -120,11 +120,11 @@ impl<R: Replier> Bindable<R> for UserDefined {
         };
 
         Bind::create(this)?
-            .bind(Self::add)?
-            .bind(Self::update)?
-            .bind(Self::remove)?
+            .bind_with(Permission::Moderator, Self::add)?
+            .bind_with(Permission::Moderator, Self::update)?
+            .bind_with(Permission::Moderator, Self::remove)?
             .bind(Self::commands)?
-            .listen(Self::listen)
+            .listen_for_commands(Self::listen)
     }
 }
 
@@ -132,10 +132,6 @@ impl UserDefined {
     // TODO support !alias
 
     fn add(&mut self, msg: &Message<impl Replier>, mut args: Arguments) -> impl Outcome {
-        if !msg.requires_permission() {
-            return;
-        }
-
         if !Self::check_body(msg, &args) {
             return;
         }
@@ -161,10 +157,6 @@ impl UserDefined {
     }
 
     fn update(&mut self, msg: &Message<impl Replier>, mut args: Arguments) -> impl Outcome {
-        if !msg.requires_permission() {
-            return;
-        }
-
         if !Self::check_body(msg, &args) {
             return;
         }
@@ -190,10 +182,6 @@ impl UserDefined {
     }
 
     fn remove(&mut self, msg: &Message<impl Replier>, mut args: Arguments) -> impl Outcome {
-        if !msg.requires_permission() {
-            return;
-        }
-
         if !args["command"].starts_with('!') {
             msg.problem(responses::InvalidSyntax {
                 error: "commands must start with !",
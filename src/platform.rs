@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use time::OffsetDateTime;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::{
+    handler::{Incoming, SharedCallable},
+    irc::Tags,
+    message::SenderPriv,
+    supervisor::Ready,
+    templates::Variant,
+    Reply, Response,
+};
+
+/// A chat service the bot can run on, see [`crate::irc::Twitch`] and [`crate::twilight::Discord`]
+///
+/// A platform turns what it receives into [`crate::Message`]s with [`dispatch`], and
+/// delivers the replies to them once they are rendered
+#[async_trait::async_trait]
+pub trait Platform: Sized + Send + Sync + 'static {
+    /// The platform's own message, handlers can get it back with [`crate::Message::source`]
+    type Message: Clone + Send + Sync + 'static;
+    /// A response that's ready to be delivered
+    type Rendered: Send + 'static;
+    /// Where the replies to a message go
+    type Outgoing: Send + 'static;
+
+    /// The name used in logs, and by [`crate::Message::platform`]
+    const NAME: &'static str;
+    /// The templates are rendered with this variant
+    const VARIANT: Variant;
+    /// Whether whispers can be delivered, they are dropped otherwise
+    const WHISPERS: bool;
+
+    /// Connects and handles messages until the connection ends
+    ///
+    /// `handlers` are the bound modules, along with their names. `ready` is
+    /// told once the platform has connected
+    async fn run(
+        &self,
+        handlers: Vec<(String, SharedCallable)>,
+        ready: Ready,
+    ) -> anyhow::Result<()>;
+
+    /// The parts of a message that every handler can see
+    fn parts(msg: &Self::Message) -> Parts;
+
+    /// What the sender of a message is allowed to do
+    fn sender_priv(msg: &Self::Message) -> SenderPriv;
+
+    /// Renders a response, this is `None` if it has no template
+    fn render(resp: Box<dyn Response>) -> Option<Self::Rendered>;
+
+    /// Delivers the replies to a message, until its handlers are done with it
+    async fn deliver(out: Self::Outgoing, responses: Responses<Self>);
+}
+
+/// The platform independent parts of a message
+pub struct Parts {
+    pub sender: Arc<str>,
+    pub target: Arc<str>,
+    pub data: Arc<str>,
+    pub timestamp: OffsetDateTime,
+    /// The IRCv3 tags, only Twitch has these
+    pub tags: Option<Arc<Tags>>,
+    /// Whether the platform shows an error when nothing is said, like for a Discord slash command
+    pub expects_reply: bool,
+}
+
+/// The replies to a message, rendered for a platform
+pub struct Responses<P> {
+    recv: UnboundedReceiver<Reply<Box<dyn Response>>>,
+    _marker: std::marker::PhantomData<fn() -> P>,
+}
+
+impl<P: Platform> Responses<P> {
+    /// Waits for the next reply, skipping any that cannot be rendered
+    pub async fn next(&mut self) -> Option<Reply<P::Rendered>> {
+        loop {
+            let resp = self.recv.recv().await?;
+            if let Some(resp) = resp.map(P::render).transpose() {
+                return Some(resp);
+            }
+        }
+    }
+}
+
+/// Runs `handlers` for a message, its replies are delivered to `out` in the background
+pub fn dispatch<'a, P: Platform>(
+    msg: P::Message,
+    handlers: impl IntoIterator<Item = &'a SharedCallable>,
+    out: P::Outgoing,
+) {
+    run::<P>(msg, handlers, out, false)
+}
+
+/// Like [`dispatch`], for a message that was edited. Only the commands run again
+pub fn dispatch_edit<'a, P: Platform>(
+    msg: P::Message,
+    handlers: impl IntoIterator<Item = &'a SharedCallable>,
+    out: P::Outgoing,
+) {
+    run::<P>(msg, handlers, out, true)
+}
+
+fn run<'a, P: Platform>(
+    msg: P::Message,
+    handlers: impl IntoIterator<Item = &'a SharedCallable>,
+    out: P::Outgoing,
+    edited: bool,
+) {
+    let (tx, recv) = tokio::sync::mpsc::unbounded_channel();
+    let msg = crate::Message::new::<P>(msg, tx);
+    for handler in handlers {
+        let msg = msg.clone();
+        // outcome is always () here
+        (handler)(match edited {
+            true => Incoming::Edit(msg),
+            false => Incoming::Message(msg),
+        });
+    }
+
+    tokio::spawn(P::deliver(out, responses(recv)));
+}
+
+/// Renders the replies from `recv` for `P`, for things that aren't messages, like events
+pub fn responses<P: Platform>(recv: UnboundedReceiver<Reply<Box<dyn Response>>>) -> Responses<P> {
+    Responses {
+        recv,
+        _marker: std::marker::PhantomData,
+    }
+}
@@ -65,23 +65,31 @@ pub mod simple_human_time {
     where
         D: ::serde::Deserializer<'de>,
     {
-        use ::serde::Deserialize as _;
+        use ::serde::{de::Error as _, Deserialize as _};
         let data = <Cow<'_, str>>::deserialize(deserializer)?;
-        // TODO validate
-        let dur = data
-            .split_terminator(',')
-            .flat_map(|s| s.trim().split_once(' '))
-            .fold(0_u64, |dur, (head, tail)| {
-                let d = head.parse::<u64>().unwrap_or(0);
-                dur + match tail {
-                    "hours" => d * 60 * 60,
-                    "minutes" => d * 60,
-                    "seconds" => d,
-                    _ => return dur,
-                }
-            });
-
-        Ok(Duration::from_secs(dur))
+        parse(&data).map(Duration::from_secs).ok_or_else(|| {
+            D::Error::custom(format!(
+                "invalid duration: '{data}', expected something like '1 hours, 30 seconds'"
+            ))
+        })
+    }
+
+    /// Parses the seconds from a list of `<number> <unit>`, an empty list is no time at all
+    fn parse(data: &str) -> Option<u64> {
+        data.split_terminator(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .try_fold(0_u64, |dur, part| {
+                let (head, tail) = part.split_once(' ')?;
+                let d = head.parse::<u64>().ok()?;
+                let unit = match tail.trim() {
+                    "hours" | "hour" => 60 * 60,
+                    "minutes" | "minute" => 60,
+                    "seconds" | "second" => 1,
+                    _ => return None,
+                };
+                dur.checked_add(d.checked_mul(unit)?)
+            })
     }
 
     #[cfg(test)]
@@ -101,5 +109,22 @@ pub mod simple_human_time {
                 assert_eq!(left, right);
             }
         }
+
+        #[test]
+        fn invalid() {
+            for input in [
+                "30s",
+                "30",
+                "thirty seconds",
+                "30 fortnights",
+                "1 hours, 30s",
+            ] {
+                assert_eq!(parse(input), None, "{input}");
+            }
+
+            assert_eq!(parse(""), Some(0));
+            assert_eq!(parse("1 minute, 30 seconds"), Some(90));
+            assert_eq!(parse("2 hours,5 minutes"), Some(2 * 60 * 60 + 5 * 60));
+        }
     }
 }
@@ -0,0 +1,189 @@
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Limit {
+    /// Twitch counts the bytes of a message
+    Bytes(usize),
+    /// Discord counts the characters of a message
+    Chars(usize),
+}
+
+impl Limit {
+    fn value(&self) -> usize {
+        match *self {
+            Self::Bytes(n) | Self::Chars(n) => n,
+        }
+    }
+
+    fn measure(&self, input: &str) -> usize {
+        match self {
+            Self::Bytes(..) => input.len(),
+            Self::Chars(..) => input.chars().count(),
+        }
+    }
+
+    /// The largest char boundary in `input` that fits in `budget`
+    fn boundary(&self, input: &str, budget: usize) -> usize {
+        match self {
+            Self::Bytes(..) if budget >= input.len() => input.len(),
+            Self::Bytes(..) => (0..=budget)
+                .rev()
+                .find(|&i| input.is_char_boundary(i))
+                .unwrap_or(0),
+            Self::Chars(..) => input
+                .char_indices()
+                .nth(budget)
+                .map_or(input.len(), |(i, _)| i),
+        }
+    }
+}
+
+/// Splits long messages into parts that fit into a platform's limit
+///
+/// Parts are split at a newline or whitespace where possible, and every part
+/// but the last ends with the continuation marker, if one was set
+#[derive(Copy, Clone, Debug)]
+pub struct Splitter {
+    limit: Limit,
+    marker: &'static str,
+}
+
+impl Splitter {
+    pub const TWITCH: Self = Self::new(Limit::Bytes(500));
+    pub const DISCORD: Self = Self::new(Limit::Chars(2000));
+
+    pub const fn new(limit: Limit) -> Self {
+        Self { limit, marker: "" }
+    }
+
+    pub const fn with_marker(self, marker: &'static str) -> Self {
+        Self { marker, ..self }
+    }
+
+    /// Reserves `n` units of the limit, e.g. for a prefix added to every part
+    pub fn reserve(self, n: usize) -> Self {
+        let limit = match self.limit {
+            Limit::Bytes(limit) => Limit::Bytes(limit.saturating_sub(n)),
+            Limit::Chars(limit) => Limit::Chars(limit.saturating_sub(n)),
+        };
+        Self { limit, ..self }
+    }
+
+    pub fn split(&self, input: &str) -> Vec<String> {
+        let mut parts = vec![];
+        let budget = self
+            .limit
+            .value()
+            .saturating_sub(self.limit.measure(self.marker));
+
+        let mut rest = input.trim();
+        while !rest.is_empty() {
+            if self.limit.measure(rest) <= self.limit.value() {
+                parts.push(rest.to_string());
+                break;
+            }
+
+            let (head, tail) = Self::split_at_boundary(rest, self.limit.boundary(rest, budget));
+            parts.push(format!("{head}{marker}", marker = self.marker));
+            rest = tail;
+        }
+        parts
+    }
+
+    fn split_at_boundary(input: &str, end: usize) -> (&str, &str) {
+        // always make progress, even if a single character doesn't fit
+        let end = match end {
+            0 => input.chars().next().map_or(0, char::len_utf8),
+            end => end,
+        };
+
+        let (head, tail) = input.split_at(end);
+        if tail.starts_with(char::is_whitespace) {
+            return (head.trim_end(), tail.trim_start());
+        }
+
+        let split = head
+            .rfind('\n')
+            .or_else(|| head.rfind(char::is_whitespace))
+            .map(|i| (head[..i].trim_end(), input[i..].trim_start()))
+            .filter(|(head, _)| !head.is_empty());
+
+        split.unwrap_or((head, tail))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short() {
+        assert_eq!(Splitter::TWITCH.split("hello world"), vec!["hello world"]);
+        assert!(Splitter::TWITCH.split("   ").is_empty());
+    }
+
+    #[test]
+    fn word_boundaries() {
+        let splitter = Splitter::new(Limit::Bytes(11));
+        assert_eq!(
+            splitter.split("hello world this is a test"),
+            vec!["hello world", "this is a", "test"]
+        );
+
+        let splitter = splitter.with_marker("..");
+        assert_eq!(
+            splitter.split("hello world this is a test"),
+            vec!["hello..", "world..", "this is a..", "test"]
+        );
+
+        assert_eq!(
+            splitter.split("abcdefghijklmnop"),
+            vec!["abcdefghi..", "jklmnop"]
+        );
+    }
+
+    #[test]
+    fn prefers_newlines() {
+        let splitter = Splitter::new(Limit::Chars(12));
+        assert_eq!(
+            splitter.split("foo bar\nbaz quux"),
+            vec!["foo bar", "baz quux"]
+        );
+    }
+
+    #[test]
+    fn multibyte_bytes() {
+        // each of these is 3 bytes
+        let input = "日本語の文章です 日本語の文章です";
+        let splitter = Splitter::new(Limit::Bytes(10));
+        let parts = splitter.split(input);
+        assert!(parts.iter().all(|part| part.len() <= 10));
+        assert_eq!(parts.concat(), input.replace(' ', ""));
+
+        let splitter = splitter.with_marker("…");
+        let parts = splitter.split(input);
+        assert!(parts.iter().all(|part| part.len() <= 10));
+        assert_eq!(parts[0], "日本…");
+        assert_eq!(parts.concat().replace('…', ""), input.replace(' ', ""));
+
+        // a single character larger than the limit still makes progress
+        let parts = Splitter::new(Limit::Bytes(2)).split("🦀🦀");
+        assert_eq!(parts, vec!["🦀", "🦀"]);
+    }
+
+    #[test]
+    fn multibyte_chars() {
+        let input = "🦀🦀🦀 crabs ünïcödé everywhere";
+        let parts = Splitter::new(Limit::Chars(9)).split(input);
+        assert_eq!(parts, vec!["🦀🦀🦀 crabs", "ünïcödé", "everywher", "e"]);
+        assert!(parts.iter().all(|part| part.chars().count() <= 9));
+
+        let parts = Splitter::DISCORD.split(&"ü ".repeat(1500));
+        assert_eq!(parts.len(), 2);
+        assert!(parts.iter().all(|part| part.chars().count() <= 2000));
+    }
+
+    #[test]
+    fn reserve() {
+        let splitter = Splitter::new(Limit::Bytes(10)).reserve(4);
+        assert_eq!(splitter.split("aaa bbb ccc"), vec!["aaa", "bbb", "ccc"]);
+    }
+}
@@ -0,0 +1,249 @@
+use std::{future::Future, sync::Arc, time::Duration};
+
+use tokio::time::Instant;
+
+/// Exponential backoff with jitter
+///
+/// Each delay is somewhere between half of and the whole current step, and the
+/// step doubles every attempt until it reaches `max`
+#[derive(Clone, Debug)]
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    attempts: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1), Duration::from_secs(5 * 60))
+    }
+}
+
+impl Backoff {
+    pub const fn new(base: Duration, max: Duration) -> Self {
+        Self {
+            base,
+            max,
+            attempts: 0,
+        }
+    }
+
+    pub const fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn next_delay(&mut self) -> Duration {
+        let step = self
+            .base
+            .checked_mul(1 << self.attempts.min(16))
+            .map_or(self.max, |step| step.min(self.max));
+        self.attempts = self.attempts.saturating_add(1);
+
+        let half = step / 2;
+        let millis = u64::try_from(half.as_millis()).unwrap_or(u64::MAX);
+        half + Duration::from_millis(fastrand::u64(0..=millis))
+    }
+
+    pub fn reset(&mut self) {
+        self.attempts = 0;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum State {
+    /// Started, but not yet ready
+    Starting,
+    /// The task said it was ready, see [`Ready`]
+    Running,
+    /// Waiting to restart after the task stopped
+    Backoff {
+        attempts: u32,
+        error: Option<String>,
+        retry_in: Duration,
+    },
+}
+
+/// The last known state of a supervised task
+#[derive(Clone)]
+pub struct Health {
+    state: Arc<parking_lot::Mutex<State>>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(parking_lot::Mutex::new(State::Starting)),
+        }
+    }
+}
+
+impl Health {
+    pub fn state(&self) -> State {
+        self.state.lock().clone()
+    }
+
+    fn set(&self, state: State) {
+        *self.state.lock() = state;
+    }
+}
+
+/// Given to a supervised task, so it can say when it's ready
+#[derive(Clone, Default)]
+pub struct Ready {
+    health: Health,
+}
+
+impl Ready {
+    pub fn ready(&self) {
+        self.health.set(State::Running)
+    }
+}
+
+/// Restarts a task whenever it stops, waiting a bit longer each time it fails in a row
+pub struct Supervisor {
+    name: &'static str,
+    backoff: Backoff,
+    health: Health,
+}
+
+impl Supervisor {
+    /// Running for at least this long resets the backoff
+    pub const HEALTHY_AFTER: Duration = Duration::from_secs(60);
+
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            backoff: Backoff::default(),
+            health: Health::default(),
+        }
+    }
+
+    pub fn with_backoff(self, backoff: Backoff) -> Self {
+        Self { backoff, ..self }
+    }
+
+    pub fn health(&self) -> Health {
+        self.health.clone()
+    }
+
+    pub async fn run<F, Fut>(mut self, mut start: F)
+    where
+        F: FnMut(Ready) -> Fut + Send,
+        Fut: Future<Output = anyhow::Result<()>> + Send,
+    {
+        let name = self.name;
+        loop {
+            log::info!("starting {name}");
+            self.health.set(State::Starting);
+
+            let ready = Ready {
+                health: self.health.clone(),
+            };
+            let started = Instant::now();
+            let error = match start(ready).await {
+                Ok(()) => {
+                    log::warn!("{name} stopped");
+                    None
+                }
+                Err(err) => {
+                    log::warn!("{name} stopped: {err}");
+                    Some(err.to_string())
+                }
+            };
+
+            if started.elapsed() >= Self::HEALTHY_AFTER {
+                self.backoff.reset();
+            }
+
+            let retry_in = self.backoff.next_delay();
+            log::warn!("restarting {name} in {retry_in:.2?}");
+            self.health.set(State::Backoff {
+                attempts: self.backoff.attempts(),
+                error,
+                retry_in,
+            });
+            tokio::time::sleep(retry_in).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(10));
+        for (min, max) in [(500, 1000), (1000, 2000), (2000, 4000), (4000, 8000)] {
+            let delay = backoff.next_delay();
+            assert!(
+                (Duration::from_millis(min)..=Duration::from_millis(max)).contains(&delay),
+                "{delay:?} not in {min}..={max}ms"
+            );
+        }
+
+        for _ in 0..100 {
+            let delay = backoff.next_delay();
+            assert!((Duration::from_secs(5)..=Duration::from_secs(10)).contains(&delay));
+        }
+
+        backoff.reset();
+        assert!(backoff.next_delay() <= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn supervisor() {
+        let supervisor = Supervisor::new("test").with_backoff(Backoff::new(
+            Duration::from_millis(1),
+            Duration::from_millis(1),
+        ));
+        let health = supervisor.health();
+        assert_eq!(health.state(), State::Starting);
+
+        let (tx, rx) = flume::unbounded();
+        let task = tokio::spawn(supervisor.run(move |_| {
+            let tx = tx.clone();
+            async move {
+                tx.send(()).unwrap();
+                anyhow::bail!("oops")
+            }
+        }));
+
+        for _ in 0..3 {
+            rx.recv_async().await.unwrap();
+        }
+        task.abort();
+
+        match health.state() {
+            State::Backoff {
+                attempts, error, ..
+            } => {
+                assert_eq!(attempts, 3);
+                assert_eq!(error.as_deref(), Some("oops"));
+            }
+            state => panic!("unexpected state: {state:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn running_once_ready() {
+        let supervisor = Supervisor::new("test");
+        let health = supervisor.health();
+
+        let (tx, rx) = flume::unbounded::<Ready>();
+        let task = tokio::spawn(supervisor.run(move |ready| {
+            let tx = tx.clone();
+            async move {
+                tx.send(ready).unwrap();
+                std::future::pending().await
+            }
+        }));
+
+        let ready = rx.recv_async().await.unwrap();
+        assert_eq!(health.state(), State::Starting);
+
+        ready.ready();
+        assert_eq!(health.state(), State::Running);
+        task.abort();
+    }
+}
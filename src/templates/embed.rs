@@ -0,0 +1,167 @@
+use super::{Environment, Parsed};
+
+/// A Discord embed, each of its text fields is a template
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmbedTemplate {
+    #[serde(default)]
+    title: Option<Parsed>,
+    #[serde(default)]
+    description: Option<Parsed>,
+    #[serde(default)]
+    url: Option<Parsed>,
+    #[serde(default)]
+    fields: Vec<FieldTemplate>,
+    #[serde(default)]
+    color: Option<Color>,
+    #[serde(default)]
+    footer: Option<Parsed>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FieldTemplate {
+    name: Parsed,
+    value: Parsed,
+    #[serde(default)]
+    inline: bool,
+}
+
+impl EmbedTemplate {
+    pub(super) fn keys(&self) -> impl Iterator<Item = &String> {
+        self.title
+            .iter()
+            .chain(&self.description)
+            .chain(&self.url)
+            .chain(&self.footer)
+            .chain(self.fields.iter().flat_map(|f| [&f.name, &f.value]))
+            .flat_map(|parsed| &parsed.keys)
+    }
+
+    pub(super) fn apply(&self, env: impl Environment) -> Embed {
+        let apply = |parsed: &Option<Parsed>| parsed.as_ref().map(|p| p.apply(&env));
+        Embed {
+            title: apply(&self.title),
+            description: apply(&self.description),
+            url: apply(&self.url),
+            fields: self
+                .fields
+                .iter()
+                .map(|field| EmbedField {
+                    name: field.name.apply(&env),
+                    value: field.value.apply(&env),
+                    inline: field.inline,
+                })
+                .collect(),
+            color: self.color.map(|Color(color)| color),
+            footer: apply(&self.footer),
+        }
+    }
+}
+
+/// A rendered embed
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Embed {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    pub fields: Vec<EmbedField>,
+    pub color: Option<u32>,
+    pub footer: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EmbedField {
+    pub name: String,
+    pub value: String,
+    pub inline: bool,
+}
+
+/// Either a number, or a `#rrggbb` string
+#[derive(Copy, Clone, Debug)]
+struct Color(u32);
+
+impl<'de> serde::Deserialize<'de> for Color {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Number(u32),
+            String(String),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Number(color) => Ok(Self(color)),
+            Repr::String(color) => color
+                .strip_prefix('#')
+                .filter(|s| s.len() == 6)
+                .and_then(|s| u32::from_str_radix(s, 16).ok())
+                .map(Self)
+                .ok_or_else(|| D::Error::custom("color must be a number or #rrggbb")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BorrowedEnv;
+
+    use super::*;
+
+    #[test]
+    fn apply() {
+        let template: EmbedTemplate = serde_yaml::from_str(
+            r##"
+title: "${name} = ${version}"
+url: "${docs}"
+description: "${description}"
+color: "#f74c00"
+fields:
+  - name: repository
+    value: "${repo}"
+    inline: true
+footer: "last updated: ${updated} ago"
+"##,
+        )
+        .unwrap();
+
+        let mut keys = template.keys().map(|s| &**s).collect::<Vec<_>>();
+        keys.sort_unstable();
+        assert_eq!(
+            keys,
+            ["description", "docs", "name", "repo", "updated", "version"]
+        );
+
+        let env = BorrowedEnv::default()
+            .insert("name", &"serde")
+            .insert("version", &"1.0.0")
+            .insert("docs", &"https://docs.rs/serde")
+            .insert("description", &"a serialization framework")
+            .insert("repo", &"https://github.com/serde-rs/serde")
+            .insert("updated", &"3 days");
+
+        assert_eq!(
+            template.apply(env),
+            Embed {
+                title: Some("serde = 1.0.0".into()),
+                description: Some("a serialization framework".into()),
+                url: Some("https://docs.rs/serde".into()),
+                fields: vec![EmbedField {
+                    name: "repository".into(),
+                    value: "https://github.com/serde-rs/serde".into(),
+                    inline: true,
+                }],
+                color: Some(0xf74c00),
+                footer: Some("last updated: 3 days ago".into()),
+            }
+        );
+
+        assert!(serde_yaml::from_str::<EmbedTemplate>("color: red").is_err());
+        assert!(serde_yaml::from_str::<EmbedTemplate>("colour: 1").is_err());
+    }
+}
@@ -9,6 +9,15 @@ pub trait Environment {
     fn resolve(&self, key: &str) -> Option<String>;
 }
 
+impl<E> Environment for &E
+where
+    E: Environment + ?Sized,
+{
+    fn resolve(&self, key: &str) -> Option<String> {
+        (**self).resolve(key)
+    }
+}
+
 impl<'f> Environment for BorrowedEnv<'f> {
     fn resolve(&self, key: &str) -> Option<String> {
         self.map.get(key).map(|s| s.show())
@@ -11,6 +11,10 @@ pub use environment::{BorrowedEnv, Environment, LimitedVec, RegisterResponse};
 mod parsed;
 use parsed::Parsed;
 
+mod embed;
+use embed::EmbedTemplate;
+pub use embed::{Embed, EmbedField};
+
 mod verify;
 pub use verify::{add_to_registry, reset_registry};
 
@@ -28,7 +32,23 @@ struct Module {
 #[derive(Debug, serde::Deserialize)]
 #[serde(transparent)]
 struct Entries {
-    cache: HashMap<Variant, Parsed>,
+    cache: HashMap<Variant, Template>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(untagged)]
+pub(crate) enum Template {
+    Text(Parsed),
+    Embed(Box<EmbedTemplate>),
+}
+
+impl Template {
+    pub(crate) fn keys(&self) -> Box<dyn Iterator<Item = &String> + '_> {
+        match self {
+            Self::Text(parsed) => Box::new(parsed.keys.iter()),
+            Self::Embed(embed) => Box::new(embed.keys()),
+        }
+    }
 }
 
 #[derive(Default, Debug, serde::Deserialize)]
@@ -53,7 +73,11 @@ impl Templates {
         T: Response + 'static,
     {
         let parsed = match self.try_find(resp.module(), resp.key(), variant) {
-            Some(parsed) => parsed,
+            Some(Template::Text(parsed)) => parsed,
+            Some(Template::Embed(..)) => {
+                log::error!("cannot render an embed as text: {}", resp as &dyn Response);
+                return None;
+            }
             None => {
                 log::error!("cannot find template: {}", resp as &dyn Response);
                 return None;
@@ -63,6 +87,17 @@ impl Templates {
         Some(parsed.apply(resp.as_environment()))
     }
 
+    /// Renders the `embed` variant of a response, if it has one
+    pub fn render_embed<T>(&self, resp: &T) -> Option<Embed>
+    where
+        T: Response + 'static,
+    {
+        match self.maybe_find(resp.module(), resp.key(), Variant::Embed)? {
+            Template::Embed(embed) => Some(embed.apply(resp.as_environment())),
+            Template::Text(..) => None,
+        }
+    }
+
     fn get_entries(&self, module: &str, key: &str) -> Option<&Entries> {
         self.modules.get(module)?.entries.get(key)
     }
@@ -75,11 +110,16 @@ impl Templates {
         Some(self.get_entries(module, key)?.cache.keys().copied())
     }
 
-    pub(crate) fn maybe_find(&self, module: &str, key: &str, variant: Variant) -> Option<&Parsed> {
+    pub(crate) fn maybe_find(
+        &self,
+        module: &str,
+        key: &str,
+        variant: Variant,
+    ) -> Option<&Template> {
         self.get_entries(module, key)?.cache.get(&variant)
     }
 
-    fn try_find(&self, module: &str, key: &str, variant: Variant) -> Option<&Parsed> {
+    fn try_find(&self, module: &str, key: &str, variant: Variant) -> Option<&Template> {
         let map = &self.get_entries(module, key)?.cache;
         match map.get(&variant) {
             Some(parsed) => Some(parsed),
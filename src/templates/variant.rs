@@ -6,6 +6,8 @@ pub enum Variant {
     #[default]
     Default,
     Discord,
+    /// A Discord embed, this is used instead of `Discord` when it exists
+    Embed,
 }
 
 impl std::fmt::Debug for Variant {
@@ -13,6 +15,7 @@ impl std::fmt::Debug for Variant {
         match self {
             Self::Default => write!(f, "default"),
             Self::Discord => write!(f, "discord"),
+            Self::Embed => write!(f, "embed"),
         }
     }
 }
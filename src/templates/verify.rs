@@ -3,6 +3,7 @@ use once_cell::sync::Lazy;
 use parking_lot::Mutex;
 use serde::Serialize;
 
+use super::{Template, Variant};
 use crate::{ext::IterExt, global::GlobalItem, handler::Response, Templates};
 use std::collections::{BTreeSet, HashMap};
 
@@ -68,8 +69,8 @@ where
             )
         })?
     {
-        let keys = match templates.maybe_find(response.module(), response.key(), variant) {
-            Some(parsed) => &parsed.keys,
+        let template = match templates.maybe_find(response.module(), response.key(), variant) {
+            Some(template) => template,
             None => {
                 anyhow::bail!(
                     "missing template for: {}@{:?}",
@@ -79,7 +80,14 @@ where
             }
         };
 
-        let left = keys.iter().map(|s| &**s).collect::<BTreeSet<_>>();
+        anyhow::ensure!(
+            matches!(template, Template::Embed(..)) == (variant == Variant::Embed),
+            "only the embed variant can be an embed, in: {}@{:?}",
+            &response as &dyn Response,
+            variant
+        );
+
+        let left = template.keys().map(|s| &**s).collect::<BTreeSet<_>>();
         anyhow::ensure!(
             left.difference(&fields).count() == 0,
             "mismatched variables in: {}@{:?} found [{}], have [{}]",
@@ -0,0 +1,140 @@
+use std::collections::BTreeSet;
+
+use twilight_model::id::{
+    marker::{ChannelMarker, GuildMarker},
+    Id,
+};
+
+use crate::config::DiscordConfig;
+
+/// What the bot is allowed to do in a channel
+#[derive(Debug, PartialEq, Eq)]
+pub enum Access<'a> {
+    Denied,
+    Allowed {
+        /// The modules active here, all of them are if this is `None`
+        modules: Option<&'a BTreeSet<String>>,
+        commands_only: bool,
+    },
+}
+
+impl<'a> Access<'a> {
+    pub fn check(
+        config: &'a DiscordConfig,
+        guild: Option<Id<GuildMarker>>,
+        channel: Id<ChannelMarker>,
+    ) -> Self {
+        const EVERYTHING: Access<'static> = Access::Allowed {
+            modules: None,
+            commands_only: false,
+        };
+
+        // direct messages aren't part of a guild
+        let guild = match guild {
+            Some(guild) if !config.guilds.is_empty() => guild,
+            _ => return EVERYTHING,
+        };
+
+        let guild = match config.guilds.get(&guild) {
+            Some(guild) => guild,
+            None => return Self::Denied,
+        };
+
+        if guild.ignore.contains(&channel)
+            || (!guild.allow.is_empty() && !guild.allow.contains(&channel))
+        {
+            return Self::Denied;
+        }
+
+        let non_empty = |set: &'a BTreeSet<String>| Some(set).filter(|set| !set.is_empty());
+        match guild.channels.get(&channel) {
+            Some(ch) => Self::Allowed {
+                modules: non_empty(&ch.modules).or_else(|| non_empty(&guild.modules)),
+                commands_only: ch.commands_only,
+            },
+            None => Self::Allowed {
+                modules: non_empty(&guild.modules),
+                commands_only: false,
+            },
+        }
+    }
+
+    /// Whether a message should be looked at, at all
+    pub fn allows_message(&self, data: &str) -> bool {
+        match self {
+            Self::Denied => false,
+            Self::Allowed { commands_only, .. } => !commands_only || data.starts_with('!'),
+        }
+    }
+
+    pub fn allows_module(&self, module: &str) -> bool {
+        match self {
+            Self::Denied => false,
+            Self::Allowed {
+                modules: Some(set), ..
+            } => set.contains(module),
+            Self::Allowed { modules: None, .. } => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn access() {
+        let config: DiscordConfig = serde_yaml::from_str(
+            r#"
+guilds:
+  1:
+    ignore: [10]
+    modules: [crates, help]
+    channels:
+      11:
+        commands_only: true
+      12:
+        modules: [spotify]
+  "2":
+    allow: [20]
+"#,
+        )
+        .unwrap();
+
+        let check = |guild: Option<u64>, channel| {
+            Access::check(&config, guild.map(Id::new), Id::new(channel))
+        };
+
+        assert_eq!(check(Some(3), 30), Access::Denied);
+        assert_eq!(check(Some(1), 10), Access::Denied);
+        assert_eq!(check(Some(2), 21), Access::Denied);
+        assert!(!Access::Denied.allows_message("!crate serde"));
+
+        let access = check(None, 40);
+        assert!(access.allows_message("hello"));
+        assert!(access.allows_module("shakespeare"));
+
+        let access = check(Some(2), 20);
+        assert!(access.allows_message("hello"));
+        assert!(access.allows_module("shakespeare"));
+
+        let access = check(Some(1), 13);
+        assert!(access.allows_message("hello"));
+        assert!(access.allows_module("crates"));
+        assert!(!access.allows_module("shakespeare"));
+
+        let access = check(Some(1), 11);
+        assert!(!access.allows_message("hello"));
+        assert!(access.allows_message("!crate serde"));
+        assert!(access.allows_module("help"));
+
+        let access = check(Some(1), 12);
+        assert!(access.allows_module("spotify"));
+        assert!(!access.allows_module("crates"));
+
+        let config = DiscordConfig::default();
+        let access = Access::check(&config, Some(Id::new(1)), Id::new(1));
+        assert!(access.allows_message("hello"));
+        assert!(access.allows_module("crates"));
+    }
+}
@@ -0,0 +1,529 @@
+use std::{sync::Arc, time::Duration};
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+
+use {
+    twilight_http::Client,
+    twilight_model::{
+        application::{
+            command::{Command as SlashCommand, CommandType},
+            interaction::{
+                application_command::{CommandData, CommandOptionValue},
+                Interaction, InteractionData,
+            },
+        },
+        channel::message::MessageFlags,
+        http::interaction::{
+            InteractionResponse, InteractionResponseData, InteractionResponseType,
+        },
+        id::{
+            marker::{
+                ApplicationMarker, ChannelMarker, GuildMarker, InteractionMarker, RoleMarker,
+                UserMarker,
+            },
+            Id,
+        },
+    },
+    twilight_util::builder::command::{CommandBuilder, StringBuilder},
+};
+
+use super::{embed::Rendered, truncate, Discord};
+use crate::{
+    handler::{Command, Commands, Kind},
+    platform::Responses,
+    split::Splitter,
+    Reply,
+};
+
+/// Discord stops waiting for a response after 3 seconds
+const RESPOND_WITHIN: Duration = Duration::from_millis(2500);
+
+/// Said to the sender when a slash command had nothing to say
+const NO_REPLY: &str = "nothing to say to that";
+
+/// Builds a slash command for every command in `commands.yaml`
+///
+/// Commands without a valid slash command name are skipped
+pub fn build(commands: &Commands) -> anyhow::Result<Vec<SlashCommand>> {
+    let mut list = vec![];
+    for command in commands.iter() {
+        let name = match slash_name(&command.command) {
+            Some(name) => name,
+            None => {
+                log::warn!("cannot make a slash command for: {}", command.command);
+                continue;
+            }
+        };
+
+        let description = match command.description.trim() {
+            "" => name,
+            description => description,
+        };
+
+        let mut builder = CommandBuilder::new(
+            name.to_string(),
+            truncate(description, 100),
+            CommandType::ChatInput,
+        );
+
+        // the rest of the command is required, like a normal argument.
+        // discord wants the required options first
+        let required = |kind: &Kind| *kind != Kind::Optional;
+        let mut args = command.args.args.iter().collect::<Vec<_>>();
+        args.sort_by_key(|arg| !required(&arg.kind));
+
+        for arg in args {
+            let description = match arg.kind {
+                Kind::Required => arg.key.clone(),
+                Kind::Optional => format!("{} (optional)", arg.key),
+                Kind::Variadic => format!("{} (the rest of the command)", arg.key),
+            };
+            builder = builder.option(
+                StringBuilder::new(arg.key.to_ascii_lowercase(), description)
+                    .required(required(&arg.kind)),
+            );
+        }
+
+        list.push(builder.validate()?.build());
+    }
+
+    list.sort_by(|left, right| left.name.cmp(&right.name));
+    Ok(list)
+}
+
+/// Replaces all of the global slash commands with `commands`
+pub async fn register(
+    client: &Client,
+    application_id: Id<ApplicationMarker>,
+    commands: &[SlashCommand],
+) -> anyhow::Result<()> {
+    client
+        .interaction(application_id)
+        .set_global_commands(commands)
+        .exec()
+        .await?;
+    log::info!("registered {} slash commands", commands.len());
+    Ok(())
+}
+
+/// The slash commands last registered by this process
+static REGISTERED: Lazy<Mutex<Option<Vec<SlashCommand>>>> = Lazy::new(|| Mutex::new(None));
+
+/// Registers `commands`, unless they are what was last registered
+///
+/// Discord sends `Ready` on every reconnect, but the commands only need to be
+/// registered again when they have changed
+pub async fn register_changed(
+    client: &Client,
+    application_id: Id<ApplicationMarker>,
+    commands: Vec<SlashCommand>,
+) -> anyhow::Result<()> {
+    if REGISTERED.lock().as_deref() == Some(&*commands) {
+        log::debug!("slash commands haven't changed, not registering them");
+        return Ok(());
+    }
+
+    register(client, application_id, &commands).await?;
+    *REGISTERED.lock() = Some(commands);
+    Ok(())
+}
+
+/// A slash command turned back into the text of the command it was built from
+pub struct Invocation {
+    pub channel_id: Id<ChannelMarker>,
+    pub guild_id: Option<Id<GuildMarker>>,
+    pub user_id: Id<UserMarker>,
+    pub roles: Vec<Id<RoleMarker>>,
+    pub sender: String,
+    pub data: String,
+    /// The module the command belongs to
+    pub module: String,
+}
+
+impl Invocation {
+    pub fn parse(commands: &Commands, interaction: &Interaction) -> Option<Self> {
+        let data = match &interaction.data {
+            Some(InteractionData::ApplicationCommand(data)) => data,
+            _ => return None,
+        };
+
+        let sender = interaction
+            .member
+            .as_ref()
+            .and_then(|member| member.user.as_ref())
+            .or(interaction.user.as_ref())?;
+
+        let roles = interaction
+            .member
+            .as_ref()
+            .map(|member| member.roles.clone())
+            .unwrap_or_default();
+
+        let (module, command) = commands
+            .with_modules()
+            .find(|(_, cmd)| slash_name(&cmd.command) == Some(&*data.name))?;
+
+        Some(Self {
+            channel_id: interaction.channel_id?,
+            guild_id: interaction.guild_id,
+            user_id: sender.id,
+            roles,
+            sender: sender.name.clone(),
+            data: Self::command_line(command, data),
+            module: module.to_string(),
+        })
+    }
+
+    fn command_line(command: &Command, data: &CommandData) -> String {
+        let mut line = command.command.clone();
+        for arg in &*command.args.args {
+            let key = arg.key.to_ascii_lowercase();
+            let value = data.options.iter().find_map(|opt| match &opt.value {
+                CommandOptionValue::String(value) if opt.name == key => Some(value.trim()),
+                _ => None,
+            });
+
+            if let Some(value) = value.filter(|s| !s.is_empty()) {
+                line.push(' ');
+                line.push_str(value);
+            }
+        }
+
+        line
+    }
+}
+
+/// Responds to a slash command with a message only the sender can see
+pub async fn notice(
+    client: &Client,
+    application_id: Id<ApplicationMarker>,
+    interaction_id: Id<InteractionMarker>,
+    token: &str,
+    content: &str,
+) {
+    let response = InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(InteractionResponseData {
+            content: Some(content.to_string()),
+            flags: Some(MessageFlags::EPHEMERAL),
+            ..Default::default()
+        }),
+    };
+    if let Err(err) = client
+        .interaction(application_id)
+        .create_response(interaction_id, token, &response)
+        .exec()
+        .await
+    {
+        log::warn!("cannot respond to interaction: {err}");
+    }
+}
+
+/// Sends the replies for a slash command
+///
+/// The first reply is the response to the interaction, the rest are followups.
+/// If nothing was said before Discord stops waiting, the response is deferred.
+///
+/// Discord shows an error when an interaction isn't responded to, so if the
+/// handlers had nothing to say the sender is told that instead. A deferred
+/// response that was never followed up is deleted
+pub async fn read_responses(
+    client: Arc<Client>,
+    application_id: Id<ApplicationMarker>,
+    interaction_id: Id<InteractionMarker>,
+    token: String,
+    mut responses: Responses<Discord>,
+) {
+    let interaction = client.interaction(application_id);
+    let mut responded = false;
+    let mut deferred = false;
+    let mut followed = false;
+
+    loop {
+        let resp = match responded {
+            true => responses.next().await,
+            false => match tokio::time::timeout(RESPOND_WITHIN, responses.next()).await {
+                Ok(resp) => resp,
+                Err(..) => {
+                    responded = true;
+                    deferred = true;
+                    let response = InteractionResponse {
+                        kind: InteractionResponseType::DeferredChannelMessageWithSource,
+                        data: None,
+                    };
+                    if let Err(err) = interaction
+                        .create_response(interaction_id, &token, &response)
+                        .exec()
+                        .await
+                    {
+                        log::warn!("cannot defer interaction response: {err}");
+                    }
+                    continue;
+                }
+            },
+        };
+
+        let resp = match resp {
+            Some(resp) => resp,
+            None => break,
+        };
+
+        let splitter = Splitter::DISCORD.with_marker("…");
+        let text = |parts: Vec<String>| parts.into_iter().map(Rendered::Text).collect();
+        let (parts, flags): (Vec<_>, _) = match resp {
+            // only the sender can see an ephemeral response, so that's a whisper
+            Reply::Problem(Rendered::Embed(embed)) | Reply::Whisper(Rendered::Embed(embed)) => {
+                (vec![Rendered::Embed(embed)], Some(MessageFlags::EPHEMERAL))
+            }
+            Reply::Say(Rendered::Embed(embed))
+            | Reply::Reply(Rendered::Embed(embed))
+            | Reply::Action(Rendered::Embed(embed)) => (vec![Rendered::Embed(embed)], None),
+
+            Reply::Say(Rendered::Text(resp)) | Reply::Reply(Rendered::Text(resp)) => {
+                (text(splitter.split(&resp)), None)
+            }
+            Reply::Problem(Rendered::Text(resp)) | Reply::Whisper(Rendered::Text(resp)) => {
+                (text(splitter.split(&resp)), Some(MessageFlags::EPHEMERAL))
+            }
+            Reply::Action(Rendered::Text(resp)) => {
+                let parts = splitter.reserve(2).split(&resp);
+                (
+                    text(parts.iter().map(|part| format!("*{part}*")).collect()),
+                    None,
+                )
+            }
+        };
+
+        for part in parts {
+            let result = match responded {
+                false => {
+                    responded = true;
+                    let (content, embeds) = match part {
+                        Rendered::Text(text) => (Some(text), None),
+                        Rendered::Embed(embed) => (None, Some(vec![*embed])),
+                    };
+                    let response = InteractionResponse {
+                        kind: InteractionResponseType::ChannelMessageWithSource,
+                        data: Some(InteractionResponseData {
+                            content,
+                            embeds,
+                            flags,
+                            ..Default::default()
+                        }),
+                    };
+                    interaction
+                        .create_response(interaction_id, &token, &response)
+                        .exec()
+                        .await
+                        .map(drop)
+                }
+                true => {
+                    followed = true;
+                    let followup = interaction.create_followup(&token);
+                    let followup = match &part {
+                        Rendered::Text(text) => followup.content(text),
+                        Rendered::Embed(embed) => followup.embeds(std::slice::from_ref(embed)),
+                    };
+                    let followup = match followup {
+                        Ok(followup) => followup,
+                        Err(err) => {
+                            log::warn!("cannot create followup: {err}");
+                            continue;
+                        }
+                    };
+                    match flags {
+                        Some(flags) => followup.flags(flags).exec().await.map(drop),
+                        None => followup.exec().await.map(drop),
+                    }
+                }
+            };
+
+            if let Err(err) = result {
+                log::warn!("cannot respond to interaction: {err}");
+            }
+        }
+    }
+
+    if !responded {
+        notice(&client, application_id, interaction_id, &token, NO_REPLY).await;
+    } else if deferred && !followed {
+        if let Err(err) = interaction.delete_response(&token).exec().await {
+            log::warn!("cannot delete deferred interaction response: {err}");
+        }
+    }
+}
+
+/// Slash command names are the command without its `!`, and have to match `^[-_a-z0-9]{1,32}$`
+fn slash_name(command: &str) -> Option<&str> {
+    let name = command.trim_start_matches('!');
+    let valid = |c: char| matches!(c, 'a'..='z' | '0'..='9' | '-' | '_');
+    (!name.is_empty() && name.len() <= 32 && name.chars().all(valid)).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+    use super::*;
+
+    const COMMANDS: &str = r#"
+crates:
+  lookup_crate:
+    command: "!crate"
+    args: "<crate>"
+    description: "tries to look up a crate on crates.io"
+twitch:
+  uptime:
+    command: "!uptime"
+    args: "<channel?>"
+    description: "gets a twitch channels uptime"
+user_defined:
+  add:
+    command: "!add"
+    args: "<command> <body..>"
+    description: "add a new command with a body"
+  invalid:
+    command: "!NotValid"
+    description: "uppercase names are not allowed"
+"#;
+
+    #[test]
+    fn slash_names() {
+        assert_eq!(slash_name("!crate"), Some("crate"));
+        assert_eq!(slash_name("!!foo-bar_baz"), Some("foo-bar_baz"));
+        assert_eq!(slash_name("!Foo"), None);
+        assert_eq!(slash_name("!"), None);
+        assert_eq!(slash_name(&format!("!{}", "a".repeat(33))), None);
+    }
+
+    /// A client for a server that answers one request with `response`, it returns the request line and body
+    async fn serve_once(
+        response: &'static [u8],
+    ) -> (Client, tokio::task::JoinHandle<(String, Vec<u8>)>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+
+            let mut request = String::new();
+            stream.read_line(&mut request).await.unwrap();
+
+            let mut len = 0;
+            loop {
+                let mut header = String::new();
+                stream.read_line(&mut header).await.unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some((key, val)) = header.split_once(':') {
+                    if key.eq_ignore_ascii_case("content-length") {
+                        len = val.trim().parse().unwrap();
+                    }
+                }
+            }
+
+            let mut body = vec![0; len];
+            stream.read_exact(&mut body).await.unwrap();
+
+            stream.get_mut().write_all(response).await.unwrap();
+            (request, body)
+        });
+
+        let client = Client::builder()
+            .token("token".into())
+            .proxy(addr.to_string(), true)
+            .ratelimiter(None)
+            .build();
+        (client, server)
+    }
+
+    #[tokio::test]
+    async fn register_commands() {
+        let (client, server) = serve_once(
+            b"HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 2\r\n\r\n[]",
+        )
+        .await;
+
+        let commands: Commands = serde_yaml::from_str(COMMANDS).unwrap();
+        let commands = build(&commands).unwrap();
+        register_changed(&client, Id::new(1), commands.clone())
+            .await
+            .unwrap();
+
+        let (request, body) = server.await.unwrap();
+
+        // the server is gone, so this would fail if they were registered again
+        register_changed(&client, Id::new(1), commands)
+            .await
+            .unwrap();
+        let mut request = request.split_whitespace();
+        assert_eq!(request.next(), Some("PUT"));
+        assert!(request
+            .next()
+            .unwrap()
+            .ends_with("/applications/1/commands"));
+
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let names = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|cmd| cmd["name"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["add", "crate", "uptime"]);
+
+        let options = |index: usize| {
+            body[index]["options"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|opt| {
+                    (
+                        opt["name"].as_str().unwrap(),
+                        opt["type"].as_u64().unwrap(),
+                        opt["required"].as_bool().unwrap_or_default(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(body[0]["description"], "add a new command with a body");
+        assert_eq!(options(0), [("command", 3, true), ("body", 3, true)]);
+        assert_eq!(options(1), [("crate", 3, true)]);
+        assert_eq!(options(2), [("channel", 3, false)]);
+    }
+
+    #[tokio::test]
+    async fn no_reply() {
+        let (client, server) = serve_once(b"HTTP/1.1 204 No Content\r\n\r\n").await;
+
+        // the handlers are done, without saying anything
+        let (tx, recv) = tokio::sync::mpsc::unbounded_channel();
+        drop(tx);
+
+        read_responses(
+            Arc::new(client),
+            Id::new(1),
+            Id::new(2),
+            "token".into(),
+            crate::platform::responses(recv),
+        )
+        .await;
+
+        let (request, body) = server.await.unwrap();
+        let mut request = request.split_whitespace();
+        assert_eq!(request.next(), Some("POST"));
+        assert!(request
+            .next()
+            .unwrap()
+            .ends_with("/interactions/2/token/callback"));
+
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["type"], 4);
+        assert_eq!(body["data"]["content"], NO_REPLY);
+        assert_eq!(body["data"]["flags"], MessageFlags::EPHEMERAL.bits());
+    }
+}
@@ -0,0 +1,174 @@
+use twilight_model::channel::embed::{Embed, EmbedField, EmbedFooter};
+
+use super::{truncate, Discord};
+use crate::{global::GlobalItem, platform::Platform as _, Response, Templates};
+
+/// A response rendered for Discord
+pub enum Rendered {
+    Text(String),
+    Embed(Box<Embed>),
+}
+
+/// Renders the embed for a response if it has one, otherwise its text
+pub fn render(resp: Box<dyn Response>) -> Option<Rendered> {
+    let templates = Templates::get();
+    if let Some(embed) = templates.render_embed(&resp) {
+        return Some(Rendered::Embed(Box::new(into_embed(embed))));
+    }
+    templates
+        .render(&resp, Discord::VARIANT)
+        .map(Rendered::Text)
+}
+
+/// Discord's limits for an embed, in characters
+mod limits {
+    pub const TITLE: usize = 256;
+    pub const DESCRIPTION: usize = 4096;
+    pub const FIELDS: usize = 25;
+    pub const FIELD_NAME: usize = 256;
+    pub const FIELD_VALUE: usize = 1024;
+    pub const FOOTER: usize = 2048;
+    /// For all of the text in an embed
+    pub const TOTAL: usize = 6000;
+}
+
+/// Discord rejects an embed that's over any of its [`limits`], so long parts are
+/// truncated. If it is still too long, fields are dropped from the end and
+/// then the description is shortened
+fn into_embed(embed: crate::templates::Embed) -> Embed {
+    // discord rejects embeds with empty strings in them
+    let non_empty = |s: Option<String>| s.filter(|s| !s.trim().is_empty());
+    let limit = |s: Option<String>, max| non_empty(s).map(|s| truncate(&s, max));
+    let len = |s: &Option<String>| s.as_deref().map_or(0, |s| s.chars().count());
+
+    let title = limit(embed.title, limits::TITLE);
+    let mut description = limit(embed.description, limits::DESCRIPTION);
+    let footer = limit(embed.footer, limits::FOOTER);
+    let mut fields = embed
+        .fields
+        .into_iter()
+        .filter(|field| !field.name.trim().is_empty() && !field.value.trim().is_empty())
+        .take(limits::FIELDS)
+        .map(|field| EmbedField {
+            inline: field.inline,
+            name: truncate(&field.name, limits::FIELD_NAME),
+            value: truncate(&field.value, limits::FIELD_VALUE),
+        })
+        .collect::<Vec<_>>();
+
+    let field_len = |field: &EmbedField| field.name.chars().count() + field.value.chars().count();
+    let mut total = len(&title) + len(&description) + len(&footer);
+    total += fields.iter().map(field_len).sum::<usize>();
+
+    while total > limits::TOTAL {
+        match fields.pop() {
+            Some(field) => total -= field_len(&field),
+            None => break,
+        }
+    }
+
+    if total > limits::TOTAL {
+        let rest = limits::TOTAL - (total - len(&description));
+        description = description.filter(|_| rest > 0).map(|s| truncate(&s, rest));
+    }
+
+    Embed {
+        author: None,
+        color: embed.color,
+        description,
+        fields,
+        footer: footer.map(|text| EmbedFooter {
+            icon_url: None,
+            proxy_icon_url: None,
+            text,
+        }),
+        image: None,
+        kind: "rich".into(),
+        provider: None,
+        thumbnail: None,
+        timestamp: None,
+        title,
+        url: non_empty(embed.url),
+        video: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates::{Embed, EmbedField};
+
+    fn total(embed: &twilight_model::channel::embed::Embed) -> usize {
+        let len = |s: &Option<String>| s.as_deref().map_or(0, |s| s.chars().count());
+        len(&embed.title)
+            + len(&embed.description)
+            + embed.footer.as_ref().map_or(0, |f| f.text.chars().count())
+            + embed
+                .fields
+                .iter()
+                .map(|f| f.name.chars().count() + f.value.chars().count())
+                .sum::<usize>()
+    }
+
+    #[test]
+    fn limits() {
+        let field = |n| EmbedField {
+            name: format!("{n}").repeat(300),
+            value: "é".repeat(2000),
+            inline: false,
+        };
+        let embed = into_embed(Embed {
+            title: Some("a".repeat(300)),
+            fields: (0..30).map(field).collect(),
+            footer: Some("c".repeat(3000)),
+            ..Embed::default()
+        });
+
+        let title = embed.title.as_deref().unwrap();
+        assert_eq!(title.chars().count(), limits::TITLE);
+        assert!(title.ends_with('…'));
+        assert_eq!(
+            embed.footer.as_ref().unwrap().text.chars().count(),
+            limits::FOOTER
+        );
+
+        // only 2 of the truncated fields fit in the total
+        assert_eq!(embed.fields.len(), 2);
+        for field in &embed.fields {
+            assert_eq!(field.name.chars().count(), limits::FIELD_NAME);
+            assert_eq!(field.value.chars().count(), limits::FIELD_VALUE);
+        }
+        assert!(total(&embed) <= limits::TOTAL);
+
+        let embed = into_embed(Embed {
+            description: Some("b".repeat(5000)),
+            fields: (0..30)
+                .map(|n| EmbedField {
+                    name: n.to_string(),
+                    value: "v".into(),
+                    inline: true,
+                })
+                .collect(),
+            ..Embed::default()
+        });
+        assert_eq!(embed.fields.len(), limits::FIELDS);
+        assert_eq!(
+            embed.description.as_deref().unwrap().chars().count(),
+            limits::DESCRIPTION
+        );
+        assert!(total(&embed) <= limits::TOTAL);
+
+        // only the description is left to shorten
+        let embed = into_embed(Embed {
+            title: Some("a".repeat(300)),
+            description: Some("b".repeat(5000)),
+            footer: Some("c".repeat(3000)),
+            ..Embed::default()
+        });
+        assert_eq!(total(&embed), limits::TOTAL);
+        assert_eq!(
+            embed.description.as_deref().unwrap().chars().count(),
+            limits::TOTAL - limits::TITLE - limits::FOOTER
+        );
+    }
+}
@@ -1,22 +1,34 @@
 use std::sync::Arc;
 use time::OffsetDateTime;
 
+use crate::message::SenderPriv;
+
+/// Something that was sent to the bot on Discord
+#[derive(Clone)]
+pub enum Received {
+    Message(Message),
+    Interaction(Interaction),
+}
+
 #[derive(Clone)]
 pub struct Message {
     pub inner: Arc<twilight_model::channel::Message>,
     pub source: Arc<str>,
     pub timestamp: OffsetDateTime,
+    pub priv_: SenderPriv,
 }
 
 impl Message {
     pub(super) fn new(
         inner: twilight_model::channel::Message,
         source: impl Into<Arc<str>>,
+        priv_: SenderPriv,
     ) -> Self {
         Self {
             inner: Arc::new(inner),
             source: source.into(),
             timestamp: time::OffsetDateTime::now_utc(),
+            priv_,
         }
     }
 }
@@ -27,3 +39,39 @@ impl std::ops::Deref for Message {
         &self.inner
     }
 }
+
+/// A slash command, along with the text of the command it was built from
+#[derive(Clone)]
+pub struct Interaction {
+    pub inner: Arc<twilight_model::application::interaction::Interaction>,
+    pub sender: Arc<str>,
+    pub source: Arc<str>,
+    pub data: Arc<str>,
+    pub timestamp: OffsetDateTime,
+    pub priv_: SenderPriv,
+}
+
+impl Interaction {
+    pub(super) fn new(
+        inner: twilight_model::application::interaction::Interaction,
+        invocation: super::commands::Invocation,
+        source: impl Into<Arc<str>>,
+        priv_: SenderPriv,
+    ) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            sender: invocation.sender.into(),
+            source: source.into(),
+            data: invocation.data.into(),
+            timestamp: time::OffsetDateTime::now_utc(),
+            priv_,
+        }
+    }
+}
+
+impl std::ops::Deref for Interaction {
+    type Target = twilight_model::application::interaction::Interaction;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
@@ -1,8 +1,5 @@
-use std::sync::Arc;
+use std::{collections::VecDeque, sync::Arc};
 
-use anyhow::Context;
-
-use tokio::sync::mpsc::UnboundedReceiver;
 use tokio_stream::StreamExt;
 
 use {
@@ -12,71 +9,185 @@ use {
         channel::message::MessageType,
         gateway::Intents,
         id::{
-            marker::{ChannelMarker, MessageMarker},
+            marker::{ChannelMarker, GuildMarker, MessageMarker, UserMarker},
             Id,
         },
     },
 };
 
-use crate::{env::EnvVar, global::GlobalItem, handler::SharedCallable, Reply, Response, Templates};
+use crate::{
+    config::DiscordConfig,
+    env::EnvVar,
+    global::GlobalItem,
+    handler::SharedCallable,
+    platform::{dispatch, dispatch_edit, Responses},
+    split::Splitter,
+    supervisor::Ready,
+    Commands, Reply,
+};
 
 mod message;
-pub use message::Message;
+pub use message::{Interaction, Message, Received};
+
+mod platform;
+pub use platform::{Discord, Outgoing};
+
+pub mod commands;
+use commands::Invocation;
+
+mod access;
+use access::Access;
+
+mod embed;
+use embed::Rendered;
+
+mod permissions;
+
+mod replies;
+use replies::{Replies, Tracked};
 
 mod state;
 use state::DiscordState;
 
-pub async fn run(handlers: Vec<SharedCallable>) -> anyhow::Result<()> {
+/// Runs the Discord bot, `handlers` are the bound modules along with their names
+pub async fn run(
+    config: &DiscordConfig,
+    handlers: Vec<(String, SharedCallable)>,
+    ready: Ready,
+) -> anyhow::Result<()> {
     let oauth_token = crate::env::SHAKEN_DISCORD_OAUTH_TOKEN::get()?;
     let client = Arc::new(twilight_http::Client::new(oauth_token.clone()));
 
     let (shard, mut events) = Shard::new(
         oauth_token,
-        Intents::GUILDS | Intents::GUILD_MESSAGES | Intents::MESSAGE_CONTENT,
+        Intents::GUILDS
+            | Intents::GUILD_MESSAGES
+            | Intents::DIRECT_MESSAGES
+            | Intents::MESSAGE_CONTENT,
     );
     shard.start().await?;
 
-    let seen = DiscordState::default();
-    let mut our_user_id = None;
+    let context = Context {
+        config,
+        handlers,
+        client: client.clone(),
+        replies: Replies::new(TRACKED_REPLIES),
+    };
+
+    let mut state = DiscordState::default();
+    let mut application_id = None;
 
     while let Some(event) = events.next().await {
+        state.update(&event);
+
         match event {
             twilight_gateway::Event::MessageCreate(msg)
                 if matches!(msg.kind, MessageType::Regular)
-                    && Some(msg.author.id) != our_user_id =>
+                    && Some(msg.author.id) != state.user().map(|user| user.id) =>
             {
-                let channel = seen
-                    .channels
-                    .update(msg.channel_id, {
-                        let client = client.clone();
-                        let id = msg.channel_id;
-                        move || {
-                            let client = client.clone();
-                            async move { get_channel_name(&client, id).await }
-                        }
-                    })
-                    .await?;
-
-                log::debug!("[{}] {}: {}", channel, msg.author.name, msg.content);
+                dispatch_message(&context, &state, msg.0, None);
+            }
+            twilight_gateway::Event::MessageUpdate(update) => {
+                let content = match &update.content {
+                    Some(content) => content,
+                    None => continue,
+                };
 
-                let (ch, id) = (msg.channel_id, msg.id);
-                let source = get_channel_name(&client, ch).await?;
+                let tracked = match context.replies.get(update.id) {
+                    // discord also sends updates when it adds embeds to a message
+                    Some(tracked) if tracked.source.content != *content => tracked,
+                    _ => continue,
+                };
 
-                let msg = Message::new(msg.0, source);
+                let mut msg = (*tracked.source).clone();
+                msg.content = content.clone();
+                dispatch_message(&context, &state, msg, Some(tracked.replies));
+            }
+            twilight_gateway::Event::MessageDelete(ev) => {
+                if let Some(tracked) = context.replies.remove(ev.id) {
+                    delete_messages(&context.client, ev.channel_id, tracked.replies);
+                }
+            }
+            twilight_gateway::Event::MessageDeleteBulk(ev) => {
+                for id in ev.ids {
+                    if let Some(tracked) = context.replies.remove(id) {
+                        delete_messages(&context.client, ev.channel_id, tracked.replies);
+                    }
+                }
+            }
+            twilight_gateway::Event::InteractionCreate(interaction) => {
+                let application_id = match application_id {
+                    Some(id) => id,
+                    None => continue,
+                };
 
-                let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
-                let msg = crate::Message::discord(msg, tx);
+                let invocation = match Invocation::parse(&Commands::get(), &interaction.0) {
+                    Some(invocation) => invocation,
+                    None => continue,
+                };
 
-                for handler in &handlers {
-                    // outcome is always () here
-                    (handler)(msg.clone());
+                // discord shows an error if the interaction isn't responded to
+                let access = Access::check(config, invocation.guild_id, invocation.channel_id);
+                if !access.allows_message(&invocation.data)
+                    || !access.allows_module(&invocation.module)
+                {
+                    let (client, id, token) =
+                        (client.clone(), interaction.id, interaction.token.clone());
+                    tokio::spawn(async move {
+                        commands::notice(&client, application_id, id, &token, NOT_AVAILABLE).await
+                    });
+                    continue;
                 }
 
-                tokio::spawn(read_responses(ch, id, rx, client.clone()));
+                let source = target(
+                    &state,
+                    invocation.guild_id,
+                    invocation.channel_id,
+                    &invocation.sender,
+                );
+                log::debug!(
+                    "[{}] {}: {} (slash command)",
+                    source,
+                    invocation.sender,
+                    invocation.data
+                );
+
+                let priv_ = state.sender_priv(
+                    config,
+                    invocation.guild_id,
+                    invocation.user_id,
+                    &invocation.roles,
+                );
+
+                let interaction = Interaction::new(interaction.0, invocation, source, priv_);
+                let out = Outgoing::Interaction {
+                    client: client.clone(),
+                    application_id,
+                    interaction_id: interaction.id,
+                    token: interaction.token.clone(),
+                };
+                dispatch::<Discord>(
+                    Received::Interaction(interaction),
+                    context.handlers(&access),
+                    out,
+                );
             }
             twilight_gateway::Event::Ready(msg) => {
                 log::debug!("discord bot name: {}, id: {}", msg.user.name, msg.user.id);
-                our_user_id.get_or_insert(msg.user.id);
+                ready.ready();
+
+                let id = *application_id.get_or_insert(msg.application.id);
+                match commands::build(&Commands::get()) {
+                    Ok(list) => {
+                        let client = client.clone();
+                        tokio::spawn(async move {
+                            if let Err(err) = commands::register_changed(&client, id, list).await {
+                                log::warn!("cannot register slash commands: {err}");
+                            }
+                        });
+                    }
+                    Err(err) => log::warn!("cannot build slash commands: {err}"),
+                }
             }
             _ => {}
         }
@@ -85,44 +196,286 @@ pub async fn run(handlers: Vec<SharedCallable>) -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn read_responses(
+/// Shortens `input` to at most `max` characters, ending it with `…` if it was
+fn truncate(input: &str, max: usize) -> String {
+    if input.chars().count() <= max {
+        return input.to_string();
+    }
+    let mut out = input
+        .chars()
+        .take(max.saturating_sub(1))
+        .collect::<String>();
+    out.push('…');
+    out
+}
+
+/// Said to the sender of a slash command that isn't allowed where it was used
+const NOT_AVAILABLE: &str = "that isn't available here";
+
+/// How many messages have their replies kept around, for edits and deletes
+const TRACKED_REPLIES: usize = 1000;
+
+struct Context<'a> {
+    config: &'a DiscordConfig,
+    handlers: Vec<(String, SharedCallable)>,
+    client: Arc<Client>,
+    replies: Replies,
+}
+
+impl<'a> Context<'a> {
+    fn handlers<'b>(
+        &'b self,
+        access: &'b Access<'_>,
+    ) -> impl Iterator<Item = &'b SharedCallable> + 'b {
+        self.handlers
+            .iter()
+            .filter(|(module, _)| access.allows_module(module))
+            .map(|(_, handler)| handler)
+    }
+}
+
+/// Runs the handlers for a message
+///
+/// If it was edited, `previous` has the replies from the earlier run, which are
+/// edited. Only the commands run again for an edit
+fn dispatch_message(
+    context: &Context<'_>,
+    state: &DiscordState,
+    msg: twilight_model::channel::Message,
+    previous: Option<Vec<Id<MessageMarker>>>,
+) {
+    let access = Access::check(context.config, msg.guild_id, msg.channel_id);
+    if !access.allows_message(&msg.content) {
+        context.replies.remove(msg.id);
+        delete_messages(
+            &context.client,
+            msg.channel_id,
+            previous.unwrap_or_default(),
+        );
+        return;
+    }
+
+    let source = target(state, msg.guild_id, msg.channel_id, &msg.author.name);
+    log::debug!("[{}] {}: {}", source, msg.author.name, msg.content);
+
+    let roles = msg.member.as_ref().map(|m| &*m.roles).unwrap_or_default();
+    let priv_ = state.sender_priv(context.config, msg.guild_id, msg.author.id, roles);
+
+    let msg = Message::new(msg, source, priv_);
+    let edited = previous.is_some();
+    let out = Outgoing::Channel {
+        source: msg.inner.clone(),
+        previous: previous.unwrap_or_default(),
+        client: context.client.clone(),
+        replies: context.replies.clone(),
+    };
+
+    let (msg, handlers) = (Received::Message(msg), context.handlers(&access));
+    match edited {
+        true => dispatch_edit::<Discord>(msg, handlers, out),
+        false => dispatch::<Discord>(msg, handlers, out),
+    }
+}
+
+/// Direct messages are `dm/@user`, everything else is `guild/#channel`
+fn target(
+    state: &DiscordState,
+    guild: Option<Id<GuildMarker>>,
     ch_id: Id<ChannelMarker>,
-    msg_id: Id<MessageMarker>,
-    mut recv: UnboundedReceiver<Reply<Box<dyn Response>>>,
+    sender: &str,
+) -> String {
+    match guild {
+        Some(..) => state.target(ch_id),
+        None => format!("dm/@{sender}"),
+    }
+}
+
+fn delete_messages(client: &Arc<Client>, ch_id: Id<ChannelMarker>, ids: Vec<Id<MessageMarker>>) {
+    if ids.is_empty() {
+        return;
+    }
+
+    let client = client.clone();
+    tokio::spawn(async move {
+        for id in ids {
+            if let Err(err) = client.delete_message(ch_id, id).exec().await {
+                log::warn!("cannot delete message {id}: {err}");
+            }
+        }
+    });
+}
+
+async fn read_responses(
+    source: Arc<twilight_model::channel::Message>,
+    previous: Vec<Id<MessageMarker>>,
+    mut responses: Responses<Discord>,
     client: Arc<Client>,
+    replies: Replies,
 ) {
-    use crate::templates::Variant::Discord;
-    while let Some(resp) = recv.recv().await {
-        let resp = resp
-            .map(|resp| Templates::get().render(&resp, Discord))
-            .transpose();
-
-        let resp = match resp {
-            Some(inner) => inner,
-            None => continue,
-        };
+    let mut out = Sending {
+        client: &client,
+        ch_id: source.channel_id,
+        previous: previous.into(),
+        sent: vec![],
+    };
 
+    while let Some(resp) = responses.next().await {
+        let splitter = Splitter::DISCORD.with_marker("…");
         match resp {
-            Reply::Say(resp) => {
-                if let Ok(ok) = client.create_message(ch_id).content(&resp) {
-                    let _ = ok.exec().await;
+            Reply::Say(embed @ Rendered::Embed(..))
+            | Reply::Action(embed @ Rendered::Embed(..)) => {
+                out.send(&embed, None).await;
+            }
+            Reply::Reply(embed @ Rendered::Embed(..))
+            | Reply::Problem(embed @ Rendered::Embed(..)) => {
+                out.send(&embed, Some(source.id)).await;
+            }
+            Reply::Say(Rendered::Text(resp)) => {
+                for part in splitter.split(&resp) {
+                    out.send(&Rendered::Text(part), None).await;
+                }
+            }
+            Reply::Action(Rendered::Text(resp)) => {
+                for part in splitter.reserve(2).split(&resp) {
+                    out.send(&Rendered::Text(format!("*{part}*")), None).await;
                 }
             }
-            Reply::Reply(resp) | Reply::Problem(resp) => {
-                if let Ok(ok) = client.create_message(ch_id).content(&resp) {
-                    let _ = ok.reply(msg_id).exec().await;
+            Reply::Reply(Rendered::Text(resp)) | Reply::Problem(Rendered::Text(resp)) => {
+                // only the first part is a reply, the rest follow it
+                for (i, part) in splitter.split(&resp).into_iter().enumerate() {
+                    let reply = (i == 0).then_some(source.id);
+                    out.send(&Rendered::Text(part), reply).await;
                 }
             }
+            Reply::Whisper(rendered) => {
+                let parts = match rendered {
+                    Rendered::Text(resp) => splitter
+                        .split(&resp)
+                        .into_iter()
+                        .map(Rendered::Text)
+                        .collect(),
+                    embed => vec![embed],
+                };
+                whisper(&client, &source, &parts, &mut out).await;
+            }
+        }
+    }
+
+    let sent = out.finish();
+    match sent.is_empty() {
+        true => drop(replies.remove(source.id)),
+        false => replies.insert(Tracked {
+            source,
+            replies: sent,
+        }),
+    }
+}
+
+/// Sends `parts` to the author of `source` in a direct message
+///
+/// If they can't be messaged, `parts` are dropped. They were meant to be private
+async fn whisper(
+    client: &Arc<Client>,
+    source: &twilight_model::channel::Message,
+    parts: &[Rendered],
+    out: &mut Sending<'_>,
+) {
+    async fn private_channel(
+        client: &Client,
+        user: Id<UserMarker>,
+    ) -> anyhow::Result<Id<ChannelMarker>> {
+        let channel = client.create_private_channel(user).exec().await?;
+        Ok(channel.model().await?.id)
+    }
+
+    // it's already private
+    if source.guild_id.is_none() {
+        for part in parts {
+            out.send(part, None).await;
+        }
+        return;
+    }
+
+    let ch_id = match private_channel(client, source.author.id).await {
+        Ok(ch_id) => ch_id,
+        Err(err) => {
+            log::warn!(
+                "cannot message {}, dropping the whisper: {err}",
+                source.author.name
+            );
+            return;
         }
+    };
+
+    // these aren't tracked, the source message is in a different channel
+    let mut dm = Sending {
+        client,
+        ch_id,
+        previous: VecDeque::new(),
+        sent: vec![],
+    };
+    for part in parts {
+        dm.send(part, None).await;
     }
 }
 
-async fn get_channel_name(client: &Client, id: Id<ChannelMarker>) -> anyhow::Result<String> {
-    let resp = client.channel(id).exec().await?;
-    let name = resp
-        .model()
-        .await?
-        .name
-        .with_context(|| "cannot find name for {id}")?;
-    Ok(name)
+/// Sends messages to a channel, editing the replies from an earlier run first
+struct Sending<'a> {
+    client: &'a Arc<Client>,
+    ch_id: Id<ChannelMarker>,
+    previous: VecDeque<Id<MessageMarker>>,
+    sent: Vec<Id<MessageMarker>>,
+}
+
+impl<'a> Sending<'a> {
+    async fn send(&mut self, rendered: &Rendered, reply: Option<Id<MessageMarker>>) {
+        let result = match self.previous.pop_front() {
+            Some(id) => self.edit(id, rendered).await,
+            None => self.create(rendered, reply).await,
+        };
+
+        match result {
+            Ok(id) => self.sent.push(id),
+            Err(err) => log::warn!("cannot send message: {err}"),
+        }
+    }
+
+    async fn create(
+        &self,
+        rendered: &Rendered,
+        reply: Option<Id<MessageMarker>>,
+    ) -> anyhow::Result<Id<MessageMarker>> {
+        let req = self.client.create_message(self.ch_id);
+        let req = match rendered {
+            Rendered::Text(text) => req.content(text)?,
+            Rendered::Embed(embed) => req.embeds(std::slice::from_ref(embed))?,
+        };
+        let req = match reply {
+            Some(id) => req.reply(id),
+            None => req,
+        };
+        Ok(req.exec().await?.model().await?.id)
+    }
+
+    async fn edit(
+        &self,
+        id: Id<MessageMarker>,
+        rendered: &Rendered,
+    ) -> anyhow::Result<Id<MessageMarker>> {
+        let req = self.client.update_message(self.ch_id, id);
+        let req = match rendered {
+            Rendered::Text(text) => req.content(Some(text))?.embeds(Some(&[]))?,
+            Rendered::Embed(embed) => req
+                .content(None)?
+                .embeds(Some(std::slice::from_ref(embed)))?,
+        };
+        req.exec().await?;
+        Ok(id)
+    }
+
+    /// Deletes the replies from an earlier run that weren't needed this time
+    fn finish(self) -> Vec<Id<MessageMarker>> {
+        delete_messages(self.client, self.ch_id, self.previous.into());
+        self.sent
+    }
 }
@@ -0,0 +1,160 @@
+use std::collections::{HashMap, HashSet};
+
+use twilight_model::{
+    guild::{Guild, Permissions, Role},
+    id::{
+        marker::{GuildMarker, RoleMarker, UserMarker},
+        Id,
+    },
+};
+
+use crate::{config::DiscordConfig, message::SenderPriv};
+
+/// The owner of a guild and the permissions each of its roles grants
+#[derive(Clone, Debug)]
+pub struct GuildRoles {
+    pub guild: Id<GuildMarker>,
+    pub owner: Id<UserMarker>,
+    pub roles: HashMap<Id<RoleMarker>, Permissions>,
+}
+
+impl From<&Guild> for GuildRoles {
+    fn from(guild: &Guild) -> Self {
+        Self::new(guild.id, guild.owner_id, &guild.roles)
+    }
+}
+
+impl GuildRoles {
+    pub fn new(guild: Id<GuildMarker>, owner: Id<UserMarker>, roles: &[Role]) -> Self {
+        Self {
+            guild,
+            owner,
+            roles: roles
+                .iter()
+                .map(|role| (role.id, role.permissions))
+                .collect(),
+        }
+    }
+
+    /// Maps a member of this guild to what they are allowed to do
+    ///
+    /// Only the configured admin users and roles are admins, as that is the
+    /// broadcaster on Twitch. The owner, anyone with `ADMINISTRATOR` or
+    /// `MANAGE_MESSAGES` and the configured moderator roles are moderators
+    pub fn sender_priv(
+        &self,
+        config: &DiscordConfig,
+        user: Id<UserMarker>,
+        roles: &[Id<RoleMarker>],
+    ) -> SenderPriv {
+        let has_role = |set: &HashSet<_>| roles.iter().any(|id| set.contains(id));
+
+        if config.admin_users.contains(&user) || has_role(&config.admin_roles) {
+            return SenderPriv::Admin;
+        }
+
+        if user == self.owner {
+            return SenderPriv::Moderator;
+        }
+
+        // everyone has the @everyone role, its id is the guild's id
+        let permissions = std::iter::once(&self.guild.cast())
+            .chain(roles)
+            .filter_map(|id| self.roles.get(id))
+            .fold(Permissions::empty(), |left, &right| left | right);
+
+        // administrators can do anything in the guild, but that doesn't make them the broadcaster
+        if permissions.intersects(Permissions::ADMINISTRATOR | Permissions::MANAGE_MESSAGES)
+            || has_role(&config.moderator_roles)
+        {
+            return SenderPriv::Moderator;
+        }
+
+        SenderPriv::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sender_priv() {
+        let guild = GuildRoles {
+            guild: Id::new(1),
+            owner: Id::new(10),
+            roles: [
+                (Id::new(1), Permissions::SEND_MESSAGES),
+                (Id::new(2), Permissions::ADMINISTRATOR),
+                (Id::new(3), Permissions::MANAGE_MESSAGES),
+                (Id::new(4), Permissions::SEND_MESSAGES),
+                (Id::new(5), Permissions::SEND_MESSAGES),
+            ]
+            .into_iter()
+            .collect(),
+        };
+
+        let config = DiscordConfig {
+            admin_users: [Id::new(12)].into_iter().collect(),
+            admin_roles: [Id::new(4)].into_iter().collect(),
+            moderator_roles: [Id::new(5)].into_iter().collect(),
+            ..DiscordConfig::default()
+        };
+
+        let check = |user, roles: &[u64]| {
+            let roles = roles.iter().copied().map(Id::new).collect::<Vec<_>>();
+            guild.sender_priv(&config, Id::new(user), &roles)
+        };
+
+        assert_eq!(check(12, &[]), SenderPriv::Admin);
+        assert_eq!(check(11, &[]), SenderPriv::None);
+        assert_eq!(check(11, &[3]), SenderPriv::Moderator);
+        assert_eq!(check(11, &[4]), SenderPriv::Admin);
+        assert_eq!(check(11, &[2, 4]), SenderPriv::Admin);
+        assert_eq!(check(11, &[5]), SenderPriv::Moderator);
+        assert_eq!(check(11, &[99]), SenderPriv::None);
+
+        let config = DiscordConfig::default();
+        let guild = GuildRoles {
+            roles: [(Id::new(1), Permissions::ADMINISTRATOR)]
+                .into_iter()
+                .collect(),
+            ..guild.clone()
+        };
+        assert_eq!(
+            guild.sender_priv(&config, Id::new(11), &[]),
+            SenderPriv::Moderator
+        );
+    }
+
+    #[test]
+    fn owner_and_administrator_are_not_admins() {
+        let guild = GuildRoles {
+            guild: Id::new(1),
+            owner: Id::new(10),
+            roles: [(Id::new(2), Permissions::ADMINISTRATOR)]
+                .into_iter()
+                .collect(),
+        };
+        let config = DiscordConfig::default();
+
+        // the owner has every permission, but is only a moderator
+        assert_eq!(
+            guild.sender_priv(&config, Id::new(10), &[]),
+            SenderPriv::Moderator
+        );
+        assert_eq!(
+            guild.sender_priv(&config, Id::new(11), &[Id::new(2)]),
+            SenderPriv::Moderator
+        );
+
+        let config = DiscordConfig {
+            admin_users: [Id::new(10)].into_iter().collect(),
+            ..DiscordConfig::default()
+        };
+        assert_eq!(
+            guild.sender_priv(&config, Id::new(10), &[]),
+            SenderPriv::Admin
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use twilight_http::Client;
+use twilight_model::id::{
+    marker::{ApplicationMarker, InteractionMarker, MessageMarker},
+    Id,
+};
+
+use crate::{
+    config::DiscordConfig,
+    handler::SharedCallable,
+    message::SenderPriv,
+    platform::{Parts, Platform, Responses},
+    supervisor::Ready,
+    templates::Variant,
+    Replier, Response,
+};
+
+use super::{commands, embed::Rendered, replies::Replies, Received};
+
+/// A Discord bot
+#[derive(Clone)]
+pub struct Discord {
+    pub config: DiscordConfig,
+}
+
+/// Where the replies to something sent on Discord go
+pub enum Outgoing {
+    /// Replies to a message, editing the replies from an earlier run of it first
+    Channel {
+        source: Arc<twilight_model::channel::Message>,
+        previous: Vec<Id<MessageMarker>>,
+        client: Arc<Client>,
+        replies: Replies,
+    },
+    /// Responses to a slash command
+    Interaction {
+        client: Arc<Client>,
+        application_id: Id<ApplicationMarker>,
+        interaction_id: Id<InteractionMarker>,
+        token: String,
+    },
+}
+
+impl<R: Replier> crate::Message<R> {
+    pub fn is_discord(&self) -> bool {
+        self.source::<Discord>().is_some()
+    }
+
+    /// The Discord message, slash commands don't have one
+    pub fn as_discord(&self) -> Option<super::Message> {
+        match self.source::<Discord>()? {
+            Received::Message(msg) => Some(msg),
+            Received::Interaction(..) => None,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Platform for Discord {
+    type Message = Received;
+    type Rendered = Rendered;
+    type Outgoing = Outgoing;
+
+    const NAME: &'static str = "discord";
+    const VARIANT: Variant = Variant::Discord;
+    const WHISPERS: bool = true;
+
+    async fn run(
+        &self,
+        handlers: Vec<(String, SharedCallable)>,
+        ready: Ready,
+    ) -> anyhow::Result<()> {
+        super::run(&self.config, handlers, ready).await
+    }
+
+    fn parts(msg: &Self::Message) -> Parts {
+        match msg {
+            Received::Message(msg) => Parts {
+                sender: msg.author.name.clone().into(),
+                target: msg.source.clone(),
+                data: msg.content.clone().into(),
+                timestamp: msg.timestamp,
+                tags: None,
+                expects_reply: false,
+            },
+            Received::Interaction(interaction) => Parts {
+                sender: interaction.sender.clone(),
+                target: interaction.source.clone(),
+                data: interaction.data.clone(),
+                timestamp: interaction.timestamp,
+                tags: None,
+                expects_reply: true,
+            },
+        }
+    }
+
+    fn sender_priv(msg: &Self::Message) -> SenderPriv {
+        // this needs the guild's roles, so it's worked out when the message arrives
+        match msg {
+            Received::Message(msg) => msg.priv_,
+            Received::Interaction(interaction) => interaction.priv_,
+        }
+    }
+
+    fn render(resp: Box<dyn Response>) -> Option<Self::Rendered> {
+        super::embed::render(resp)
+    }
+
+    async fn deliver(out: Self::Outgoing, responses: Responses<Self>) {
+        match out {
+            Outgoing::Channel {
+                source,
+                previous,
+                client,
+                replies,
+            } => super::read_responses(source, previous, responses, client, replies).await,
+            Outgoing::Interaction {
+                client,
+                application_id,
+                interaction_id,
+                token,
+            } => {
+                commands::read_responses(client, application_id, interaction_id, token, responses)
+                    .await
+            }
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use twilight_model::{
+    channel::Message,
+    id::{marker::MessageMarker, Id},
+};
+
+use crate::bounded::Bounded;
+
+/// A message that was replied to, and the replies to it
+#[derive(Clone)]
+pub struct Tracked {
+    pub source: Arc<Message>,
+    pub replies: Vec<Id<MessageMarker>>,
+}
+
+/// The replies to recent messages, so they can follow edits and deletes
+///
+/// Only `capacity` messages are kept, the least recently used ones are forgotten first
+#[derive(Clone)]
+pub struct Replies {
+    inner: Arc<Mutex<Bounded<Id<MessageMarker>, Tracked>>>,
+}
+
+impl Replies {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Bounded::new(capacity))),
+        }
+    }
+
+    pub fn insert(&self, tracked: Tracked) {
+        self.inner.lock().insert(tracked.source.id, tracked);
+    }
+
+    pub fn get(&self, id: Id<MessageMarker>) -> Option<Tracked> {
+        self.inner.lock().get(&id).cloned()
+    }
+
+    pub fn remove(&self, id: Id<MessageMarker>) -> Option<Tracked> {
+        self.inner.lock().remove(&id)
+    }
+}
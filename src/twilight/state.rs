@@ -1,46 +1,210 @@
-use std::{collections::HashMap, future::Future, sync::Arc};
-use tokio::sync::Mutex;
+use std::collections::HashMap;
 
-use twilight_model::id::{marker::ChannelMarker, Id};
+use twilight_model::{
+    channel::Channel,
+    gateway::event::Event,
+    guild::Role,
+    id::{
+        marker::{ChannelMarker, GuildMarker, RoleMarker, UserMarker},
+        Id,
+    },
+    user::CurrentUser,
+};
 
+use super::permissions::GuildRoles;
+use crate::{config::DiscordConfig, message::SenderPriv};
+
+struct CachedGuild {
+    name: String,
+    roles: GuildRoles,
+}
+
+struct CachedChannel {
+    name: Option<String>,
+    guild: Option<Id<GuildMarker>>,
+}
+
+/// What we know about Discord, kept up to date from gateway events
 #[derive(Default)]
 pub struct DiscordState {
-    pub channels: Map<ChannelMarker>,
+    guilds: HashMap<Id<GuildMarker>, CachedGuild>,
+    channels: HashMap<Id<ChannelMarker>, CachedChannel>,
+    user: Option<CurrentUser>,
 }
 
-#[derive(Clone)]
-pub struct Map<T, V = String> {
-    map: Arc<Mutex<HashMap<Id<T>, Arc<V>>>>,
-}
+impl DiscordState {
+    pub fn update(&mut self, event: &Event) {
+        match event {
+            Event::Ready(ready) => self.user = Some(ready.user.clone()),
+            Event::UserUpdate(user) => self.user = Some(user.0.clone()),
 
-impl<T, V> Default for Map<T, V> {
-    fn default() -> Self {
-        Self {
-            map: Default::default(),
+            Event::GuildCreate(guild) => {
+                self.add_guild(guild.id, &guild.name, GuildRoles::from(&guild.0));
+                for channel in guild.channels.iter().chain(&guild.threads) {
+                    self.add_channel(channel.id, Some(guild.id), channel.name.as_deref());
+                }
+            }
+            Event::GuildUpdate(guild) => {
+                if let Some(cached) = self.guilds.get_mut(&guild.id) {
+                    cached.name = guild.name.clone();
+                    cached.roles = GuildRoles::new(guild.id, guild.owner_id, &guild.roles);
+                }
+            }
+            Event::GuildDelete(guild) => self.remove_guild(guild.id),
+
+            Event::ChannelCreate(channel) => self.add_guild_channel(channel),
+            Event::ChannelUpdate(channel) => self.add_guild_channel(channel),
+            Event::ThreadCreate(channel) => self.add_guild_channel(channel),
+            Event::ThreadUpdate(channel) => self.add_guild_channel(channel),
+            Event::ChannelDelete(channel) => self.remove_channel(channel.id),
+            Event::ThreadDelete(thread) => self.remove_channel(thread.id),
+
+            Event::RoleCreate(ev) => self.set_role(ev.guild_id, &ev.role),
+            Event::RoleUpdate(ev) => self.set_role(ev.guild_id, &ev.role),
+            Event::RoleDelete(ev) => {
+                if let Some(cached) = self.guilds.get_mut(&ev.guild_id) {
+                    cached.roles.roles.remove(&ev.role_id);
+                }
+            }
+            _ => {}
         }
     }
-}
 
-impl<T> Map<T>
-where
-    T: Send,
-{
-    pub async fn update<S, Fut>(
+    pub fn user(&self) -> Option<&CurrentUser> {
+        self.user.as_ref()
+    }
+
+    /// The name of a channel, as `guild/#channel`
+    ///
+    /// Channels that haven't been seen are named after their id
+    pub fn target(&self, channel: Id<ChannelMarker>) -> String {
+        let cached = match self.channels.get(&channel) {
+            Some(cached) => cached,
+            None => return channel.to_string(),
+        };
+
+        let name = cached.name.as_deref().unwrap_or("unknown");
+        match cached.guild.and_then(|id| self.guilds.get(&id)) {
+            Some(guild) => format!("{}/#{name}", guild.name),
+            None => format!("#{name}"),
+        }
+    }
+
+    /// Maps a guild member to what they are allowed to do, see [`GuildRoles::sender_priv`]
+    ///
+    /// The configured admin users are admins outside of a guild too
+    pub fn sender_priv(
         &self,
-        id: Id<T>,
-        vacant: impl Fn() -> Fut + Send + Sync,
-    ) -> anyhow::Result<Arc<String>>
-    where
-        S: Into<String> + Send,
-        Fut: Future<Output = anyhow::Result<S>> + Send,
-    {
-        use std::collections::hash_map::Entry;
-        match self.map.lock().await.entry(id) {
-            Entry::Occupied(t) => Ok(t.get().clone()),
-            Entry::Vacant(t) => {
-                let data = vacant().await?.into();
-                Ok(t.insert(Arc::new(data)).clone())
-            }
+        config: &DiscordConfig,
+        guild: Option<Id<GuildMarker>>,
+        user: Id<UserMarker>,
+        roles: &[Id<RoleMarker>],
+    ) -> SenderPriv {
+        if config.admin_users.contains(&user) {
+            return SenderPriv::Admin;
+        }
+
+        guild
+            .and_then(|id| self.guilds.get(&id))
+            .map_or(SenderPriv::None, |guild| {
+                guild.roles.sender_priv(config, user, roles)
+            })
+    }
+
+    fn add_guild(&mut self, id: Id<GuildMarker>, name: &str, roles: GuildRoles) {
+        let name = name.to_string();
+        self.guilds.insert(id, CachedGuild { name, roles });
+    }
+
+    fn remove_guild(&mut self, id: Id<GuildMarker>) {
+        self.guilds.remove(&id);
+        self.channels.retain(|_, channel| channel.guild != Some(id));
+    }
+
+    fn add_guild_channel(&mut self, channel: &Channel) {
+        self.add_channel(channel.id, channel.guild_id, channel.name.as_deref())
+    }
+
+    fn add_channel(
+        &mut self,
+        id: Id<ChannelMarker>,
+        guild: Option<Id<GuildMarker>>,
+        name: Option<&str>,
+    ) {
+        let name = name.map(ToString::to_string);
+        self.channels.insert(id, CachedChannel { name, guild });
+    }
+
+    fn remove_channel(&mut self, id: Id<ChannelMarker>) {
+        self.channels.remove(&id);
+    }
+
+    fn set_role(&mut self, guild: Id<GuildMarker>, role: &Role) {
+        if let Some(cached) = self.guilds.get_mut(&guild) {
+            cached.roles.roles.insert(role.id, role.permissions);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use twilight_model::guild::Permissions;
+
+    use super::*;
+
+    #[test]
+    fn target() {
+        let mut state = DiscordState::default();
+        let roles = GuildRoles::new(Id::new(1), Id::new(10), &[]);
+        state.add_guild(Id::new(1), "museun", roles);
+        state.add_channel(Id::new(2), Some(Id::new(1)), Some("general"));
+        state.add_channel(Id::new(3), None, Some("elsewhere"));
+
+        assert_eq!(state.target(Id::new(2)), "museun/#general");
+        assert_eq!(state.target(Id::new(3)), "#elsewhere");
+        assert_eq!(state.target(Id::new(4)), "4");
+
+        state.remove_guild(Id::new(1));
+        assert_eq!(state.target(Id::new(2)), "2");
+        assert_eq!(state.target(Id::new(3)), "#elsewhere");
+    }
+
+    #[test]
+    fn roles() {
+        let mut state = DiscordState::default();
+        let roles = GuildRoles::new(Id::new(1), Id::new(10), &[]);
+        state.add_guild(Id::new(1), "museun", roles);
+
+        let config = DiscordConfig::default();
+        let check = |state: &DiscordState| {
+            state.sender_priv(&config, Some(Id::new(1)), Id::new(11), &[Id::new(5)])
+        };
+        assert_eq!(check(&state), SenderPriv::None);
+
+        let role = |permissions| {
+            let mut roles = GuildRoles::new(Id::new(1), Id::new(10), &[]);
+            roles.roles.insert(Id::new(5), permissions);
+            roles
+        };
+
+        state.add_guild(Id::new(1), "museun", role(Permissions::MANAGE_MESSAGES));
+        assert_eq!(check(&state), SenderPriv::Moderator);
+
+        state.guilds.get_mut(&Id::new(1)).unwrap().roles = role(Permissions::ADMINISTRATOR);
+        assert_eq!(check(&state), SenderPriv::Moderator);
+
+        assert_eq!(
+            state.sender_priv(&config, None, Id::new(11), &[Id::new(5)]),
+            SenderPriv::None
+        );
+
+        let config = DiscordConfig {
+            admin_users: [Id::new(11)].into_iter().collect(),
+            ..DiscordConfig::default()
+        };
+        assert_eq!(
+            state.sender_priv(&config, None, Id::new(11), &[]),
+            SenderPriv::Admin
+        );
+    }
+}